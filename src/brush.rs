@@ -0,0 +1,114 @@
+use rand::Rng;
+use voronoice::Point;
+
+use super::utils::distance;
+
+/// Number of candidate sites tried every time the brush sprays.
+const SPRAY_ATTEMPTS: usize = 30;
+
+/// Paints and erases sites within a circular area.
+#[derive(Debug)]
+pub struct Brush {
+    pub radius: f64,
+    /// Target number of sites per unit of area.
+    pub density: f64,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Brush {
+            radius: 0.2,
+            density: 400.0,
+        }
+    }
+}
+
+impl Brush {
+    /// Minimum distance kept between a painted site and any other site.
+    /// This is the spacing of a square grid with the brush density, so repeatedly spraying the same area does not pile up near-duplicate sites.
+    pub fn min_distance(&self) -> f64 {
+        1.0 / self.density.sqrt()
+    }
+
+    /// Sprays sites around `center`, rejecting candidates closer than [`Brush::min_distance`] to an existing site (Poisson-disk rejection).
+    /// Returns the number of sites added.
+    pub fn spray<R: Rng>(&self, center: &Point, sites: &mut Vec<Point>, rng: &mut R) -> usize {
+        let min_distance = self.min_distance();
+
+        // only sites around the brush can reject a candidate
+        let mut nearby: Vec<Point> = sites.iter()
+            .filter(|s| distance(s, center) < self.radius + min_distance)
+            .cloned()
+            .collect();
+
+        let mut added = 0;
+        for _ in 0..SPRAY_ATTEMPTS {
+            // uniform sample within the brush disk
+            let r = self.radius * rng.gen::<f64>().sqrt();
+            let angle = rng.gen::<f64>() * std::f64::consts::TAU;
+            let candidate = Point { x: center.x + r * angle.cos(), y: center.y + r * angle.sin() };
+
+            if nearby.iter().all(|s| distance(s, &candidate) >= min_distance) {
+                nearby.push(candidate.clone());
+                sites.push(candidate);
+                added += 1;
+            }
+        }
+
+        added
+    }
+
+    /// Removes the sites within the brush disk around `center`.
+    /// Returns the number of sites removed.
+    pub fn erase(&self, center: &Point, sites: &mut Vec<Point>) -> usize {
+        let len = sites.len();
+        sites.retain(|s| distance(s, center) > self.radius);
+        len - sites.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn spray_keeps_min_distance() {
+        let brush = Brush::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sites = vec![];
+
+        // spraying over the same spot should saturate instead of collapsing sites
+        for _ in 0..50 {
+            brush.spray(&Point { x: 0.0, y: 0.0 }, &mut sites, &mut rng);
+        }
+
+        assert!(sites.len() > 0);
+        for (i, a) in sites.iter().enumerate() {
+            for b in sites.iter().skip(i + 1) {
+                assert!(distance(a, b) >= brush.min_distance());
+            }
+        }
+    }
+
+    #[test]
+    fn spray_rejects_existing_sites() {
+        let brush = Brush { radius: 0.001, density: 1.0 };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sites = vec![Point { x: 0.0, y: 0.0 }];
+
+        assert_eq!(0, brush.spray(&Point { x: 0.0, y: 0.0 }, &mut sites, &mut rng));
+        assert_eq!(1, sites.len());
+    }
+
+    #[test]
+    fn erase_removes_sites_within_radius() {
+        let brush = Brush { radius: 1.0, density: 1.0 };
+        let mut sites = vec![Point { x: 0.0, y: 0.0 }, Point { x: 0.5, y: 0.5 }, Point { x: 2.0, y: 0.0 }];
+
+        assert_eq!(2, brush.erase(&Point { x: 0.0, y: 0.0 }, &mut sites));
+        assert_eq!(1, sites.len());
+        assert_eq!(2.0, sites[0].x);
+    }
+}
@@ -0,0 +1,418 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::camera::{Camera, CameraProjection, DepthCalculation, OrthographicProjection, PerspectiveProjection, ScalingMode},
+};
+use voronoice::Point;
+
+use super::{
+    utils,
+    Mouse,
+    State,
+};
+
+pub const CAMERA_Y: f32 = 6.0;
+
+/// Label of the system moving the camera. Systems unprojecting the cursor run after it.
+pub const CAMERA_SYSTEM: &str = "camera";
+
+const MIN_DISTANCE: f32 = 0.01;
+const MAX_DISTANCE: f32 = 1000.0;
+/// Zoom factor for each scroll wheel line.
+const ZOOM_STEP: f32 = 1.1;
+/// Screen heights per second panned with the arrow keys.
+const PAN_SPEED: f32 = 0.5;
+/// Margin left around the area fitted in the view.
+const FIT_MARGIN: f32 = 1.1;
+/// Held with the middle button to drag the camera, so a plain middle click still inspects a cell.
+pub const DRAG_MODIFIER: KeyCode = KeyCode::LControl;
+/// Radians orbited per pixel of mouse drag.
+const ORBIT_SPEED: f32 = 0.005;
+/// Maximum tilt away from looking straight down, so the diagram never turns edge-on.
+const MAX_TILT: f32 = 80.0 * std::f32::consts::PI / 180.0;
+/// Distance of the orthographic camera to the focus. Zoom does not move it, only changes the projection scale.
+const ORTHOGRAPHIC_CAMERA_DISTANCE: f32 = 100.0;
+
+/// Keys bookmarking camera views, bookmark `i` is saved with control + `BOOKMARK_KEYS[i]`.
+pub const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+    KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+    KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
+/// Marks the camera rendering the diagram.
+pub struct MainCamera;
+
+/// What the main camera is looking at. The camera transform is derived from it every frame.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraView {
+    /// Point on the diagram plane at the center of the view
+    pub focus: Vec3,
+    /// Distance from the camera to the focus
+    pub distance: f32,
+    /// When true, dragging the mouse tilts and rotates the camera around the focus instead of panning
+    pub orbit: bool,
+    /// Rotation around the diagram normal (Y)
+    pub yaw: f32,
+    /// Angle away from looking straight down at the diagram
+    pub tilt: f32,
+    /// Orthographic instead of perspective projection. Zoom then scales the projection, keeping the view the same size as the perspective one.
+    pub orthographic: bool,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        CameraView {
+            focus: Vec3::ZERO,
+            distance: CAMERA_Y,
+            orbit: false,
+            yaw: 0.0,
+            tilt: 0.0,
+            orthographic: false,
+        }
+    }
+}
+
+/// Rotation of a camera looking down Y at the diagram.
+pub fn top_down_rotation() -> Quat {
+    let mut t = Transform::from_translation(Vec3::new(0.000001, CAMERA_Y, 0.0))
+        .looking_at(Vec3::default(), Vec3::Y);
+    // roll camera so Z point up, and X right
+    t.rotate(Quat::from_rotation_ypr(0.0, 0.0, 180f32.to_radians()));
+    t.rotation
+}
+
+/// Intersects a ray with the diagram plane (y = 0). Returns `None` if the ray is parallel to or points away from the plane.
+pub fn ray_plane_intersection(origin: Vec3, direction: Vec3) -> Option<Vec3> {
+    let denominator = direction.dot(Vec3::Y);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let t = -origin.dot(Vec3::Y) / denominator;
+    if t < 0.0 {
+        return None;
+    }
+
+    let mut p = origin + t * direction;
+    p.y = 0.0;
+    Some(p)
+}
+
+/// Projects a point on screen (in pixels, origin at the bottom left) onto the diagram plane.
+pub fn screen_to_plane(screen_pos: Vec2, window: &Window, camera: &Camera, camera_transform: &Transform) -> Option<Vec3> {
+    let screen_size = Vec2::new(window.width(), window.height());
+
+    // normalize screen coords (-1 to 1)
+    let ndc = 2.0 * (screen_pos / screen_size) - Vec2::new(1.0, 1.0);
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+
+    // the ray under the cursor goes from the near (z = 0) to the far (z = 1) plane
+    let near = ndc_to_world.transform_point3(ndc.extend(0.0));
+    let far = ndc_to_world.transform_point3(ndc.extend(1.0));
+    ray_plane_intersection(near, far - near)
+}
+
+/// Vertical field of view of the perspective camera.
+fn fov() -> f32 {
+    PerspectiveProjection::default().fov
+}
+
+impl CameraView {
+    /// Half of the view height on the focus plane.
+    pub fn half_height(&self) -> f32 {
+        self.distance * (fov() / 2.0).tan()
+    }
+
+    pub fn orthographic_projection(&self) -> OrthographicProjection {
+        OrthographicProjection {
+            // with a fixed vertical scaling the projection is 2 * scale high
+            scale: self.half_height(),
+            scaling_mode: ScalingMode::FixedVertical,
+            depth_calculation: DepthCalculation::Distance,
+            ..Default::default()
+        }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(self.yaw) * top_down_rotation() * Quat::from_rotation_x(self.tilt)
+    }
+
+    /// Screen right and up directions along the diagram plane.
+    fn plane_axes(&self) -> (Vec3, Vec3) {
+        let rotation = self.rotation();
+        let mut up = rotation * Vec3::Y;
+        up.y = 0.0;
+        (rotation * Vec3::X, up.normalize())
+    }
+
+    pub fn transform(&self) -> Transform {
+        let rotation = self.rotation();
+        let distance = if self.orthographic { ORTHOGRAPHIC_CAMERA_DISTANCE } else { self.distance };
+        Transform {
+            translation: self.focus + rotation * (Vec3::Z * distance),
+            rotation,
+            ..Default::default()
+        }
+    }
+
+    /// Moves the camera towards (`factor` < 1) or away from `anchor` keeping it at the same place on screen.
+    pub fn zoom_at(&mut self, anchor: Vec3, factor: f32) {
+        let distance = (self.distance * factor).max(MIN_DISTANCE).min(MAX_DISTANCE);
+        let factor = distance / self.distance;
+        self.focus = anchor + (self.focus - anchor) * factor;
+        self.distance = distance;
+    }
+
+    /// Centers the view on `points` and zooms so they are all visible.
+    pub fn fit(&mut self, points: &[Point], aspect_ratio: f32) {
+        if points.is_empty() {
+            return;
+        }
+
+        let (right, up) = self.plane_axes();
+        let positions: Vec<Vec3> = points.iter().map(|p| Vec3::from(utils::point_to_f32_vec(p))).collect();
+
+        // extents of the points along screen axes
+        let (mut min, mut max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+        for p in positions.iter() {
+            let screen = Vec2::new(p.dot(right), p.dot(up));
+            min = min.min(screen);
+            max = max.max(screen);
+        }
+
+        let center = (min + max) / 2.0;
+        let half_size = (max - min) / 2.0 * FIT_MARGIN;
+        let tan = (fov() / 2.0).tan();
+
+        self.focus = right * center.x + up * center.y;
+        self.distance = (half_size.y / tan)
+            .max(half_size.x / (tan * aspect_ratio))
+            .max(MIN_DISTANCE)
+            .min(MAX_DISTANCE);
+    }
+}
+
+/// Projection matrix of `view` for a window of `width` by `height`.
+/// Bevy only recomputes the camera matrix when the window is resized, so it is set from this whenever the view changes.
+fn projection_matrix(view: &CameraView, width: f32, height: f32) -> (Mat4, DepthCalculation) {
+    if view.orthographic {
+        let mut projection = view.orthographic_projection();
+        projection.update(width, height);
+        (projection.get_projection_matrix(), projection.depth_calculation())
+    } else {
+        let mut projection = PerspectiveProjection::default();
+        projection.update(width, height);
+        (projection.get_projection_matrix(), projection.depth_calculation())
+    }
+}
+
+/// Swaps the projection component, which bevy uses to recompute the matrix when the window is resized.
+fn set_projection(commands: &mut Commands, entity: Entity, view: &CameraView) {
+    if view.orthographic {
+        commands.entity(entity)
+            .remove::<PerspectiveProjection>()
+            .insert(view.orthographic_projection());
+    } else {
+        commands.entity(entity)
+            .remove::<OrthographicProjection>()
+            .insert(PerspectiveProjection::default());
+    }
+}
+
+/// Pans with the arrow keys or control + middle mouse drag, zooms with the scroll wheel (centered on the cursor) or W/S, and fits the view with F.
+/// In orbit mode (T), control + middle mouse drag tilts and rotates the camera around the focus. Tab toggles orthographic projection.
+/// Control + number saves the view in a bookmark, number alone goes back to it.
+pub fn control_camera(
+    mut commands: Commands,
+    mut state: ResMut<State>,
+    input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    mouse_query: Query<&Mouse>,
+    mut drag_anchor: Local<Option<Vec3>>,
+    mut camera_query: Query<(Entity, &mut CameraView, &mut Transform, &mut Camera), With<MainCamera>>,
+    mut orthographic_query: Query<&mut OrthographicProjection, With<MainCamera>>) {
+
+    let mouse = mouse_query.iter().next().unwrap();
+    let (entity, mut view, mut transform, mut camera) = if let Some(c) = camera_query.iter_mut().next() {
+        c
+    } else {
+        return;
+    };
+    let aspect_ratio = windows.get_primary().map_or(1.0, |w| w.width() / w.height());
+
+    let orbiting = view.orbit && !input.pressed(KeyCode::LShift);
+    let mouse_delta = mouse_motion.iter().fold(Vec2::ZERO, |delta, m| delta + m.delta);
+
+    // mouse drag keeps the point grabbed under the cursor
+    let dragging = input.pressed(DRAG_MODIFIER) && state.prompt.is_none();
+    if mouse_button_input.just_pressed(MouseButton::Middle) && dragging && mouse.on_plane && !orbiting {
+        *drag_anchor = Some(mouse.world_pos + mouse.pane_offset);
+    } else if !mouse_button_input.pressed(MouseButton::Middle) || orbiting {
+        *drag_anchor = None;
+    }
+
+    if let Some(anchor) = *drag_anchor {
+        if mouse.on_plane {
+            view.focus += anchor - (mouse.world_pos + mouse.pane_offset);
+        }
+    } else if orbiting && dragging && mouse_button_input.pressed(MouseButton::Middle) {
+        view.yaw -= mouse_delta.x * ORBIT_SPEED;
+        view.tilt = (view.tilt - mouse_delta.y * ORBIT_SPEED).max(0.0).min(MAX_TILT);
+    }
+
+    // zoom around the cursor, or the center of the view if the cursor is not over the diagram
+    let zoom_anchor = if mouse.on_plane { mouse.world_pos + mouse.pane_offset } else { view.focus };
+    for event in mouse_wheel.iter() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.0,
+        };
+        view.zoom_at(zoom_anchor, ZOOM_STEP.powf(-lines));
+    }
+
+    // keys are text while a prompt is open
+    if state.prompt.is_none() {
+        let (right, up) = view.plane_axes();
+        let pan_step = PAN_SPEED * time.delta_seconds() * 2.0 * view.half_height();
+
+        // control + up/down changes the number of sites
+        if !input.pressed(KeyCode::LControl) {
+            if input.pressed(KeyCode::Left) {
+                view.focus -= right * pan_step;
+            } else if input.pressed(KeyCode::Right) {
+                view.focus += right * pan_step;
+            }
+
+            if input.pressed(KeyCode::Up) {
+                view.focus += up * pan_step;
+            } else if input.pressed(KeyCode::Down) {
+                view.focus -= up * pan_step;
+            }
+        }
+
+        if input.just_pressed(KeyCode::T) {
+            // leaving orbit mode goes back to looking straight down
+            view.orbit = !view.orbit;
+            view.yaw = 0.0;
+            view.tilt = 0.0;
+        }
+
+        if input.just_pressed(KeyCode::Tab) {
+            view.orthographic = !view.orthographic;
+            set_projection(&mut commands, entity, &view);
+        }
+
+        for (i, key) in BOOKMARK_KEYS.iter().enumerate() {
+            if input.just_pressed(*key) {
+                if input.pressed(KeyCode::LControl) {
+                    state.camera_bookmarks[i] = Some(*view);
+                    println!("Camera bookmark {} saved: {:?}", i + 1, *view);
+                } else if let Some(bookmark) = state.camera_bookmarks[i] {
+                    if bookmark.orthographic != view.orthographic {
+                        set_projection(&mut commands, entity, &bookmark);
+                    }
+                    *view = bookmark;
+                }
+            }
+        }
+
+        let focus = view.focus;
+        if input.pressed(KeyCode::W) {
+            view.zoom_at(focus, 0.98);
+        } else if input.pressed(KeyCode::S) {
+            view.zoom_at(focus, 1.0 / 0.98);
+        } else if input.just_pressed(KeyCode::R) {
+            // projection is kept as it is
+            *view = CameraView {
+                orthographic: view.orthographic,
+                ..Default::default()
+            };
+        }
+
+        if input.just_pressed(KeyCode::F) {
+            if input.pressed(KeyCode::LShift) {
+                // fit selected cell
+                let cell_vertices = state.voronoi.as_ref()
+                    .zip(state.selected_site)
+                    .filter(|(v, s)| *s < v.sites().len())
+                    .map(|(v, s)| v.cell(s).iter_vertices().cloned().collect::<Vec<Point>>());
+
+                if let Some(vertices) = cell_vertices {
+                    view.fit(&vertices, aspect_ratio);
+                } else {
+                    println!("No site selected");
+                }
+            } else {
+                view.fit(&utils::bounding_box_corners(&state.bounding_box), aspect_ratio);
+            }
+        }
+    }
+
+    *transform = view.transform();
+
+    // orthographic zoom is the projection scale, kept in the component for when the window is resized
+    if view.orthographic {
+        for mut projection in orthographic_query.iter_mut() {
+            let scale = view.half_height();
+            if projection.scale != scale {
+                projection.scale = scale;
+            }
+        }
+    }
+
+    // only touch the camera when the projection changed, so it is not marked as changed every frame
+    if let Some(window) = windows.get_primary() {
+        let (matrix, depth_calculation) = projection_matrix(&view, window.width(), window.height());
+        if camera.projection_matrix != matrix {
+            camera.projection_matrix = matrix;
+            camera.depth_calculation = depth_calculation;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_plane_intersection_expect_none_when_parallel_or_away() {
+        assert_eq!(None, ray_plane_intersection(Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 1.0)));
+        assert_eq!(None, ray_plane_intersection(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)));
+        assert_eq!(None, ray_plane_intersection(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)));
+    }
+
+    #[test]
+    fn ray_plane_intersection_expect_point_on_plane() {
+        let p = ray_plane_intersection(Vec3::new(0.0, 2.0, 0.0), Vec3::new(1.0, -1.0, 0.5)).unwrap();
+        assert_eq!(Vec3::new(2.0, 0.0, 1.0), p);
+
+        // the ray may start below the plane
+        let p = ray_plane_intersection(Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 2.0, 1.0)).unwrap();
+        assert_eq!(Vec3::new(1.0, 0.0, 0.5), p);
+    }
+
+    #[test]
+    fn restored_orthographic_bookmark_expect_orthographic_matrix() {
+        let view = CameraView::default();
+        let bookmark = CameraView { orthographic: true, distance: 3.0, ..view };
+
+        // perspective divides by depth, orthographic does not
+        let (perspective, _) = projection_matrix(&view, 1280.0, 720.0);
+        assert_eq!(-1.0, perspective.z_axis.w);
+
+        let (orthographic, _) = projection_matrix(&bookmark, 1280.0, 720.0);
+        assert_eq!(0.0, orthographic.z_axis.w);
+        assert_eq!(1.0, orthographic.w_axis.w);
+
+        // the top of the view is half the bookmarked view height above the focus, at any depth
+        for depth in [1.0, 50.0].iter() {
+            let top = orthographic.transform_point3(Vec3::new(0.0, bookmark.half_height(), -depth));
+            assert!((top.y - 1.0).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use rand::Rng;
+use voronoice::Point;
+
+use super::{
+    utils::{self, distance},
+    ColorBundle,
+    State,
+    prompt::parse_point,
+};
+
+/// Most sites placed along a single curve.
+const MAX_SITES: usize = 100_000;
+
+/// Number of straight segments a Bezier curve is drawn and measured with.
+const BEZIER_SEGMENTS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveKind {
+    /// Straight segments between the clicked points
+    Polyline,
+    /// Single Bezier curve with the clicked points as control points
+    Bezier,
+}
+
+/// Points clicked with the curve tool and how sites are placed along the curve.
+#[derive(Debug)]
+pub struct CurveTool {
+    pub kind: CurveKind,
+    pub points: Vec<Point>,
+    /// Number of sites placed along the curve
+    pub count: usize,
+    /// Largest distance a site is moved away from the curve, perpendicular to it
+    pub jitter: f64,
+}
+
+impl CurveTool {
+    pub fn new(kind: CurveKind) -> Self {
+        CurveTool {
+            kind,
+            points: Vec::new(),
+            count: 50,
+            jitter: 0.0,
+        }
+    }
+
+    pub fn toggle_kind(&mut self) {
+        self.kind = match self.kind {
+            CurveKind::Polyline => CurveKind::Bezier,
+            CurveKind::Bezier => CurveKind::Polyline,
+        };
+    }
+
+    /// The curve as a polyline.
+    pub fn path(&self) -> Vec<Point> {
+        match self.kind {
+            CurveKind::Polyline => self.points.clone(),
+            CurveKind::Bezier => bezier(&self.points, BEZIER_SEGMENTS),
+        }
+    }
+
+    pub fn sites<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<Point> {
+        place_along(&self.path(), self.count, self.jitter, rng)
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{:?}, {} points, {} sites, jitter {:.4}", self.kind, self.points.len(), self.count, self.jitter)
+    }
+}
+
+/// Bezier curve with the given control points (De Casteljau's algorithm), sampled at `segments + 1` evenly spaced parameters.
+pub fn bezier(control_points: &[Point], segments: usize) -> Vec<Point> {
+    if control_points.len() < 2 {
+        return control_points.to_vec();
+    }
+
+    (0..=segments)
+        .map(|i| {
+            let t = i as f64 / segments as f64;
+            let mut points = control_points.to_vec();
+            for n in (1..points.len()).rev() {
+                for j in 0..n {
+                    points[j] = Point {
+                        x: points[j].x + t * (points[j + 1].x - points[j].x),
+                        y: points[j].y + t * (points[j + 1].y - points[j].y),
+                    };
+                }
+            }
+            points[0].clone()
+        })
+        .collect()
+}
+
+/// `count` sites evenly spaced by arc length along the polyline, including both ends, each moved by up to `jitter` perpendicular to the segment it is on.
+pub fn place_along<R: Rng + ?Sized>(path: &[Point], count: usize, jitter: f64, rng: &mut R) -> Vec<Point> {
+    // repeated points would give segments without a direction
+    let segments: Vec<(&Point, &Point, f64)> = path.windows(2)
+        .map(|w| (&w[0], &w[1], distance(&w[0], &w[1])))
+        .filter(|(_, _, length)| *length > 0.0)
+        .collect();
+    if segments.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let total: f64 = segments.iter().map(|(_, _, length)| length).sum();
+    // arc length where the current segment starts
+    let (mut segment, mut start) = (0, 0.0);
+    let mut sites = Vec::with_capacity(count);
+    for i in 0..count {
+        let s = if count > 1 { total * i as f64 / (count - 1) as f64 } else { total / 2.0 };
+        while segment + 1 < segments.len() && start + segments[segment].2 < s {
+            start += segments[segment].2;
+            segment += 1;
+        }
+
+        let (a, b, length) = segments[segment];
+        let t = ((s - start) / length).min(1.0);
+        let (dx, dy) = ((b.x - a.x) / length, (b.y - a.y) / length);
+        let offset = if jitter > 0.0 { rng.gen_range(-jitter..=jitter) } else { 0.0 };
+        sites.push(Point {
+            x: a.x + t * (b.x - a.x) - dy * offset,
+            y: a.y + t * (b.y - a.y) + dx * offset,
+        });
+    }
+
+    sites
+}
+
+/// Parses the number of sites, up to [`MAX_SITES`], and the jitter, typed as `count, jitter`.
+pub fn parse_settings(text: &str) -> Option<(usize, f64)> {
+    parse_point(text)
+        .filter(|p| p.x >= 1.0 && p.x <= MAX_SITES as f64 && p.x.fract() == 0.0 && p.y >= 0.0)
+        .map(|p| (p.x as usize, p.y))
+}
+
+/// Clicked points and the curve through them.
+pub struct CurveOverlay;
+
+const CONTROL_COLOR: Color = Color::GRAY;
+const CURVE_COLOR: Color = Color::CYAN;
+
+/// Redraws the curve when its points or kind change.
+pub fn update_curve_overlay(
+    state: Res<State>,
+    mut rendered: Local<Option<(CurveKind, Vec<(f64, f64)>)>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<Entity, With<CurveOverlay>>) {
+
+    let key = state.curve.as_ref().map(|c| (c.kind, c.points.iter().map(|p| (p.x, p.y)).collect()));
+    if *rendered == key {
+        return;
+    }
+    *rendered = key;
+
+    for e in query.iter() {
+        commands.entity(e).despawn();
+    }
+
+    let curve = match state.curve.as_ref() {
+        Some(c) if c.points.len() > 1 => c,
+        _ => return,
+    };
+
+    let transform = Transform::from_translation(Vec3::Y * state.render_layers.tool_height());
+    if curve.kind == CurveKind::Bezier {
+        commands
+            .spawn_bundle(ColorBundle {
+                mesh: meshes.add(utils::build_line_strip(&curve.points, false, CONTROL_COLOR)),
+                transform,
+                ..Default::default()
+            })
+            .insert(CurveOverlay);
+    }
+
+    commands
+        .spawn_bundle(ColorBundle {
+            mesh: meshes.add(utils::build_line_strip(&curve.path(), false, CURVE_COLOR)),
+            transform,
+            ..Default::default()
+        })
+        .insert(CurveOverlay);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn place_along_expect_even_arc_length_spacing() {
+        let path = [Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 1.0, y: 1.0 }];
+        let mut rng = StdRng::seed_from_u64(0);
+        let sites = place_along(&path, 5, 0.0, &mut rng);
+
+        let expected = [(0.0, 0.0), (0.5, 0.0), (1.0, 0.0), (1.0, 0.5), (1.0, 1.0)];
+        assert_eq!(expected.to_vec(), sites.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn place_along_jitter_is_perpendicular() {
+        let path = [Point { x: 0.0, y: 0.0 }, Point { x: 2.0, y: 0.0 }];
+        let mut rng = StdRng::seed_from_u64(0);
+        let sites = place_along(&path, 11, 0.1, &mut rng);
+
+        for (i, p) in sites.iter().enumerate() {
+            assert!((p.x - i as f64 * 0.2).abs() < 1e-12);
+            assert!(p.y.abs() <= 0.1);
+        }
+    }
+
+    #[test]
+    fn parse_settings_expect_count_in_range() {
+        assert_eq!(Some((50, 0.01)), parse_settings("50, 0.01"));
+        assert_eq!(None, parse_settings("0, 0.01"));
+        assert_eq!(None, parse_settings("2.5, 0"));
+        assert_eq!(None, parse_settings("100000000, 0"));
+    }
+
+    #[test]
+    fn bezier_expect_ends_and_midpoint() {
+        let control_points = [Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 2.0 }, Point { x: 2.0, y: 0.0 }];
+        let curve = bezier(&control_points, 2);
+
+        assert_eq!(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)], curve.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>());
+    }
+}
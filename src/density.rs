@@ -0,0 +1,227 @@
+use bevy::render::texture::{Texture, TextureFormat};
+use rand::Rng;
+use voronoice::{BoundingBox, Point};
+
+/// Attempts per requested site before sampling gives up, so a blank image does not loop forever.
+const SAMPLE_ATTEMPTS_PER_SITE: usize = 100;
+
+/// Darkness of an image stretched over the bounding box, used as site density.
+/// The image is shown upright in the default view: its columns go along `y` and its rows go down `x`.
+#[derive(Debug)]
+pub struct DensityMap {
+    width: usize,
+    height: usize,
+    /// Darkness of each pixel, from 0 (white) to 1 (black), row by row from the top
+    darkness: Vec<f64>,
+}
+
+/// Luminance of a pixel from 0 to 1: its first channel for grayscale and the average of its color channels otherwise.
+/// Channels of 2 bytes are little endian.
+fn luminance(pixel: &[u8], channel_bytes: usize, channels: usize) -> f64 {
+    let channel = |i: usize| if channel_bytes == 2 {
+        u16::from_le_bytes([pixel[2 * i], pixel[2 * i + 1]]) as f64 / u16::MAX as f64
+    } else {
+        pixel[i] as f64 / u8::MAX as f64
+    };
+
+    // the second channel of two is alpha
+    if channels >= 3 {
+        (channel(0) + channel(1) + channel(2)) / 3.0
+    } else {
+        channel(0)
+    }
+}
+
+impl DensityMap {
+    pub fn new(width: usize, height: usize, darkness: Vec<f64>) -> Self {
+        assert_eq!(width * height, darkness.len());
+        DensityMap { width, height, darkness }
+    }
+
+    /// Reads 8 and 16 bit grayscale and color images, the formats images are loaded as.
+    pub fn from_texture(texture: &Texture) -> Result<Self, String> {
+        // bytes per channel and channels per pixel
+        let (channel_bytes, channels) = match texture.format {
+            TextureFormat::R8Unorm => (1, 1),
+            TextureFormat::Rg8Unorm => (1, 2),
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+                | TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => (1, 4),
+            TextureFormat::R16Uint => (2, 1),
+            TextureFormat::Rg16Uint => (2, 2),
+            TextureFormat::Rgba16Uint => (2, 4),
+            format => return Err(format!("Density image format {:?} is not supported", format)),
+        };
+
+        let (width, height) = (texture.size.width as usize, texture.size.height as usize);
+        if width * height == 0 {
+            return Err("Density image has no pixels".to_string());
+        }
+        if texture.data.len() != width * height * channel_bytes * channels {
+            return Err(format!("Density image has {} bytes, expected {}", texture.data.len(), width * height * channel_bytes * channels));
+        }
+
+        let darkness = texture.data
+            .chunks_exact(channel_bytes * channels)
+            .map(|pixel| 1.0 - luminance(pixel, channel_bytes, channels))
+            .collect();
+
+        Ok(DensityMap::new(width, height, darkness))
+    }
+
+    /// Pixel under `point`, if it is inside the bounding box.
+    fn pixel(&self, point: &Point, bounding_box: &BoundingBox) -> Option<(usize, usize)> {
+        let center = bounding_box.center();
+        let u = (point.y - center.y) / bounding_box.height() + 0.5;
+        let v = 0.5 - (point.x - center.x) / bounding_box.width();
+        if u < 0.0 || u >= 1.0 || v < 0.0 || v >= 1.0 {
+            return None;
+        }
+
+        Some(((u * self.width as f64) as usize, (v * self.height as f64) as usize))
+    }
+
+    /// Center of a pixel in diagram coordinates.
+    fn pixel_center(&self, column: usize, row: usize, bounding_box: &BoundingBox) -> Point {
+        let center = bounding_box.center();
+        let u = (column as f64 + 0.5) / self.width as f64;
+        let v = (row as f64 + 0.5) / self.height as f64;
+        Point { x: center.x + (0.5 - v) * bounding_box.width(), y: center.y + (u - 0.5) * bounding_box.height() }
+    }
+
+    pub fn density(&self, point: &Point, bounding_box: &BoundingBox) -> f64 {
+        self.pixel(point, bounding_box).map_or(0.0, |(column, row)| self.darkness[row * self.width + column])
+    }
+
+    /// Samples up to `size` sites in the bounding box, with density proportional to the image darkness (rejection sampling).
+    pub fn sample<R: Rng + ?Sized>(&self, bounding_box: &BoundingBox, size: usize, rng: &mut R) -> Vec<Point> {
+        let center = bounding_box.center();
+        let (width, height) = (bounding_box.width(), bounding_box.height());
+        let mut sites = Vec::with_capacity(size);
+
+        for _ in 0..size * SAMPLE_ATTEMPTS_PER_SITE {
+            if sites.len() == size {
+                break;
+            }
+
+            let candidate = Point {
+                x: center.x + (rng.gen::<f64>() - 0.5) * width,
+                y: center.y + (rng.gen::<f64>() - 0.5) * height,
+            };
+            if rng.gen::<f64>() < self.density(&candidate, bounding_box) {
+                sites.push(candidate);
+            }
+        }
+
+        sites
+    }
+
+    /// Darkness-weighted centroid of each cell (weighted Voronoi stippling), computed over the pixels whose center is in the cell.
+    /// `cells` are the convex polygons of the cells of `sites`, in the same order. Sites of cells over white or no pixels do not move.
+    pub fn weighted_centroids(&self, sites: &[Point], cells: &[Vec<Point>], bounding_box: &BoundingBox) -> Vec<Point> {
+        sites.iter().zip(cells.iter())
+            .map(|(site, cell)| self.weighted_centroid(cell, bounding_box).unwrap_or_else(|| site.clone()))
+            .collect()
+    }
+
+    fn weighted_centroid(&self, cell: &[Point], bounding_box: &BoundingBox) -> Option<Point> {
+        if cell.len() < 3 {
+            return None;
+        }
+
+        // only pixels within the cell extent can be in it
+        let (mut min, mut max) = (Point { x: f64::MAX, y: f64::MAX }, Point { x: f64::MIN, y: f64::MIN });
+        for v in cell {
+            min = Point { x: min.x.min(v.x), y: min.y.min(v.y) };
+            max = Point { x: max.x.max(v.x), y: max.y.max(v.y) };
+        }
+        let clamp = |p: &Point| {
+            let center = bounding_box.center();
+            let (hw, hh) = (bounding_box.width() / 2.0, bounding_box.height() / 2.0);
+            // pixels include their top and left edges only
+            Point { x: p.x.max(center.x - hw + 1e-9).min(center.x + hw), y: p.y.max(center.y - hh).min(center.y + hh - 1e-9) }
+        };
+        let (first_column, last_row) = self.pixel(&clamp(&min), bounding_box)?;
+        let (last_column, first_row) = self.pixel(&clamp(&max), bounding_box)?;
+
+        let (mut weight, mut x, mut y) = (0.0, 0.0, 0.0);
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                let p = self.pixel_center(column, row, bounding_box);
+                let w = self.darkness[row * self.width + column];
+                if w > 0.0 && contains(cell, &p) {
+                    weight += w;
+                    x += w * p.x;
+                    y += w * p.y;
+                }
+            }
+        }
+
+        if weight > 0.0 {
+            Some(Point { x: x / weight, y: y / weight })
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns true if `p` is inside the convex polygon, whichever its winding.
+fn contains(polygon: &[Point], p: &Point) -> bool {
+    let n = polygon.len();
+    let mut sign = 0.0;
+    for i in 0..n {
+        let (a, b) = (&polygon[i], &polygon[(i + 1) % n]);
+        let cross = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+        if cross != 0.0 {
+            if sign != 0.0 && cross.signum() != sign {
+                return false;
+            }
+            sign = cross.signum();
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn sample_expect_sites_only_where_dark() {
+        // left column black, right column white; columns go along y
+        let map = DensityMap::new(2, 1, vec![1.0, 0.0]);
+        let bounding_box = BoundingBox::new_centered_square(2.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let sites = map.sample(&bounding_box, 100, &mut rng);
+
+        assert_eq!(100, sites.len());
+        assert!(sites.iter().all(|s| s.y < 0.0));
+    }
+
+    #[test]
+    fn luminance_expect_8_and_16_bit_channels() {
+        assert_eq!(1.0, luminance(&[255], 1, 1));
+        assert_eq!(0.0, luminance(&[0, 255], 1, 2));
+        assert!((luminance(&[0, 0, 255, 255, 255, 255, 0, 0], 2, 4) - 2.0 / 3.0).abs() < 1e-12);
+        // a mid gray 16 bit pixel is not read as its low byte
+        assert_eq!(1.0, luminance(&[255, 255], 2, 1));
+        assert!((luminance(&[0, 128], 2, 1) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn weighted_centroid_moves_toward_dark_pixels() {
+        let map = DensityMap::new(2, 1, vec![1.0, 0.0]);
+        let bounding_box = BoundingBox::new_centered_square(2.0);
+        let cell = vec![
+            Point { x: -1.0, y: -1.0 },
+            Point { x: 1.0, y: -1.0 },
+            Point { x: 1.0, y: 1.0 },
+            Point { x: -1.0, y: 1.0 },
+        ];
+
+        let centroids = map.weighted_centroids(&[Point { x: 0.0, y: 0.0 }], &[cell], &bounding_box);
+        assert_eq!(0.0, centroids[0].x);
+        assert_eq!(-0.5, centroids[0].y);
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use voronoice::{Voronoi, VoronoiCell};
+
+use super::{
+    ColorBundle,
+    State,
+    preview::{cell_changed, site_key},
+    voronoi_cell_mesh_generator::VoronoiCellMeshGenerator,
+};
+
+/// Outline of a cell that differs from the previous undo state.
+pub struct DiffOverlay;
+
+/// Cells that differ between two diagrams, matched by site coordinates.
+#[derive(Debug, Default)]
+pub struct CellDiff {
+    /// Sites of the current diagram that are not in the previous one
+    pub added: Vec<usize>,
+    /// Sites of the previous diagram that are not in the current one
+    pub removed: Vec<usize>,
+    /// Sites of the current diagram whose cell changed shape
+    pub reshaped: Vec<usize>,
+}
+
+impl CellDiff {
+    pub fn new(previous: &Voronoi, current: &Voronoi) -> Self {
+        let previous_sites: HashMap<(u64, u64), usize> = previous.sites().iter().enumerate()
+            .map(|(i, p)| (site_key(p), i))
+            .collect();
+        let current_sites: HashMap<(u64, u64), usize> = current.sites().iter().enumerate()
+            .map(|(i, p)| (site_key(p), i))
+            .collect();
+
+        let mut diff = CellDiff::default();
+        for (site, p) in current.sites().iter().enumerate() {
+            match previous_sites.get(&site_key(p)) {
+                None => diff.added.push(site),
+                Some(previous_site) => if cell_changed(&current.cell(site), &previous.cell(*previous_site)) {
+                    diff.reshaped.push(site);
+                },
+            }
+        }
+
+        diff.removed = previous.sites().iter().enumerate()
+            .filter(|(_, p)| !current_sites.contains_key(&site_key(p)))
+            .map(|(i, _)| i)
+            .collect();
+
+        diff
+    }
+}
+
+fn color_added(_i: usize) -> Color {
+    Color::GREEN
+}
+
+fn color_removed(_i: usize) -> Color {
+    Color::rgb(1.0, 0.3, 0.3)
+}
+
+fn color_reshaped(_i: usize) -> Color {
+    Color::YELLOW
+}
+
+fn color_added_fill(_i: usize) -> Color {
+    Color::rgba(0.0, 1.0, 0.0, 0.2)
+}
+
+fn color_removed_fill(_i: usize) -> Color {
+    Color::rgba(1.0, 0.3, 0.3, 0.2)
+}
+
+fn spawn_diff_cell(commands: &mut Commands, meshes: &mut ResMut<Assets<Mesh>>, height: f32, cell: &VoronoiCell, coloring: fn(usize) -> Color, fill: Option<fn(usize) -> Color>) {
+    let transform = Transform::from_translation(Vec3::Y * height);
+    let outline_generator = VoronoiCellMeshGenerator {
+        cell,
+        coloring
+    };
+
+    commands
+        .spawn_bundle(
+            ColorBundle {
+                mesh: meshes.add(outline_generator.build_outline_mesh()),
+                transform,
+                ..Default::default()
+        })
+        .insert(DiffOverlay);
+
+    if let Some(coloring) = fill {
+        let fill_generator = VoronoiCellMeshGenerator {
+            cell,
+            coloring
+        };
+
+        commands
+            .spawn_bundle(
+                ColorBundle {
+                    mesh: meshes.add(fill_generator.build_voronoi_mesh()),
+                    transform,
+                    ..ColorBundle::translucent()
+            })
+            .insert(DiffOverlay);
+    }
+}
+
+/// While diff mode is on, outlines the cells added, removed (previous shape) and reshaped since the head of the undo list.
+pub fn update_diff_overlay(
+    state: Res<State>,
+    mut rendered: Local<Option<u64>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<Entity, With<DiffOverlay>>) {
+
+    let key = Some(state.generation).filter(|_| state.diff);
+    if *rendered == key {
+        return;
+    }
+    *rendered = key;
+
+    for e in query.iter() {
+        commands.entity(e).despawn();
+    }
+
+    let (previous, current) = match (state.undo_list.front(), state.voronoi.as_ref()) {
+        (Some(p), Some(c)) if state.diff => (p, c),
+        _ => return,
+    };
+
+    // above the diagram layers so outlines lying on cell edges are not hidden by them
+    let height = state.render_layers.tool_height();
+    let diff = CellDiff::new(previous, current);
+    println!("Diff against previous state: {} added, {} removed, {} reshaped", diff.added.len(), diff.removed.len(), diff.reshaped.len());
+
+    for site in diff.removed.iter() {
+        spawn_diff_cell(&mut commands, &mut meshes, height, &previous.cell(*site), color_removed, Some(color_removed_fill));
+    }
+    for site in diff.reshaped.iter() {
+        spawn_diff_cell(&mut commands, &mut meshes, height, &current.cell(*site), color_reshaped, None);
+    }
+    for site in diff.added.iter() {
+        spawn_diff_cell(&mut commands, &mut meshes, height, &current.cell(*site), color_added, Some(color_added_fill));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use voronoice::{BoundingBox, Point, VoronoiBuilder};
+
+    use super::*;
+
+    fn build(sites: Vec<Point>) -> Voronoi {
+        VoronoiBuilder::default()
+            .set_bounding_box(BoundingBox::new_centered_square(2.0))
+            .set_sites(sites)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn cell_diff_expect_added_removed_and_reshaped_sites() {
+        let sites = vec![
+            Point { x: -0.6, y: -0.5 },
+            Point { x: 0.5, y: -0.4 },
+            Point { x: 0.4, y: 0.6 },
+            Point { x: -0.5, y: 0.5 },
+        ];
+        let previous = build(sites.clone());
+
+        let same = CellDiff::new(&previous, &build(sites.clone()));
+        assert!(same.added.is_empty() && same.removed.is_empty() && same.reshaped.is_empty());
+
+        // the last site is replaced by one in the middle, which takes area from the others
+        let mut moved = sites[..3].to_vec();
+        moved.push(Point { x: 0.1, y: 0.05 });
+        let diff = CellDiff::new(&previous, &build(moved));
+        assert_eq!(vec![3], diff.added);
+        assert_eq!(vec![3], diff.removed);
+        assert!(!diff.reshaped.is_empty());
+        assert!(diff.reshaped.iter().all(|s| *s < 3));
+    }
+}
@@ -3,13 +3,13 @@
 layout(location = 0) in vec3 Vertex_Position;
 layout(location = 1) in vec3 Vertex_Normal;
 layout(location = 2) in vec2 Vertex_Uv;
-layout(location = 3) in vec3 Vertex_Color;
+layout(location = 3) in vec4 Vertex_Color;
 //layout(location = 4) in vec3 Vertex_Centroid;
 
 layout(location = 0) out vec3 v_Position;
 layout(location = 1) out vec3 v_Normal;
 layout(location = 2) out vec2 v_Uv;
-layout(location = 3) out vec3 v_Color;
+layout(location = 3) out vec4 v_Color;
 
 layout(set = 0, binding = 0) uniform CameraViewProj {
     mat4 ViewProj;
@@ -0,0 +1,143 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::{Indices, Mesh}, pipeline::PrimitiveTopology},
+};
+use voronoice::{BoundingBox, Point};
+
+use super::utils;
+
+/// Grids with more lines than this are not rendered.
+const MAX_GRID_LINES: usize = 200;
+
+/// Snapping grid for site placement, aligned to the center of the bounding box.
+#[derive(Debug)]
+pub struct Grid {
+    pub enabled: bool,
+    pub spacing: f64,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid {
+            enabled: false,
+            spacing: 0.1,
+        }
+    }
+}
+
+impl Grid {
+    /// Returns the grid intersection closest to `point`.
+    pub fn snap(&self, point: &Point, bounding_box: &BoundingBox) -> Point {
+        let center = bounding_box.center();
+        Point {
+            x: center.x + ((point.x - center.x) / self.spacing).round() * self.spacing,
+            y: center.y + ((point.y - center.y) / self.spacing).round() * self.spacing,
+        }
+    }
+
+    /// Steps spacing up in a 1-2-5 sequence, so typed coordinates stay round numbers.
+    pub fn increase_spacing(&mut self) {
+        let (mantissa, exponent) = Self::decompose(self.spacing);
+        self.spacing = match mantissa {
+            1 => 2.0,
+            2 => 5.0,
+            _ => 10.0,
+        } * 10f64.powi(exponent);
+    }
+
+    /// Steps spacing down in a 1-2-5 sequence.
+    pub fn decrease_spacing(&mut self) {
+        let (mantissa, exponent) = Self::decompose(self.spacing);
+        self.spacing = match mantissa {
+            5 => 2.0,
+            2 => 1.0,
+            _ => 0.5,
+        } * 10f64.powi(exponent);
+    }
+
+    fn decompose(spacing: f64) -> (u32, i32) {
+        let exponent = spacing.log10().floor() as i32;
+        let mantissa = (spacing / 10f64.powi(exponent)).round() as u32;
+
+        // log10 may land just below an exact power of ten
+        if mantissa >= 10 {
+            (mantissa / 10, exponent + 1)
+        } else {
+            (mantissa, exponent)
+        }
+    }
+
+    /// Builds a line list with the grid lines within `bounding_box`, or `None` if there would be too many lines to render.
+    pub fn build_mesh(&self, bounding_box: &BoundingBox, color: Color) -> Option<Mesh> {
+        let center = bounding_box.center();
+        let half_width = bounding_box.width() / 2.0;
+        let half_height = bounding_box.height() / 2.0;
+        let columns = (half_width / self.spacing).floor() as i64;
+        let rows = (half_height / self.spacing).floor() as i64;
+
+        if (2 * columns + 1 + 2 * rows + 1) as usize > MAX_GRID_LINES {
+            return None;
+        }
+
+        let mut positions: Vec<[f32; 3]> = vec![];
+        for i in -columns..=columns {
+            let x = center.x + i as f64 * self.spacing;
+            positions.push(utils::point_to_f32_vec(&Point { x, y: center.y - half_height }));
+            positions.push(utils::point_to_f32_vec(&Point { x, y: center.y + half_height }));
+        }
+        for i in -rows..=rows {
+            let y = center.y + i as f64 * self.spacing;
+            positions.push(utils::point_to_f32_vec(&Point { x: center.x - half_width, y }));
+            positions.push(utils::point_to_f32_vec(&Point { x: center.x + half_width, y }));
+        }
+
+        let num_of_vertices = positions.len();
+        let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+        mesh.set_indices(Some(Indices::U32((0..num_of_vertices as u32).collect())));
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; num_of_vertices]);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; num_of_vertices]);
+        mesh.set_attribute("Vertex_Color", vec![utils::color_to_f32_vec(color); num_of_vertices]);
+        Some(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!((expected - actual).abs() < 1e-12, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn snap_expect_closest_intersection_aligned_to_center() {
+        let grid = Grid { enabled: true, spacing: 0.1 };
+        let bounding_box = BoundingBox::new(Point { x: 0.05, y: 0.0 }, 2.0, 2.0);
+        let snapped = grid.snap(&Point { x: 0.23, y: -0.26 }, &bounding_box);
+
+        assert_close(0.25, snapped.x);
+        assert_close(-0.3, snapped.y);
+    }
+
+    #[test]
+    fn spacing_steps_in_1_2_5_sequence() {
+        let mut grid = Grid { enabled: true, spacing: 0.1 };
+        for expected in [0.2, 0.5, 1.0, 2.0].iter() {
+            grid.increase_spacing();
+            assert_close(*expected, grid.spacing);
+        }
+        for expected in [1.0, 0.5, 0.2, 0.1, 0.05].iter() {
+            grid.decrease_spacing();
+            assert_close(*expected, grid.spacing);
+        }
+    }
+
+    #[test]
+    fn decompose_expect_mantissa_below_10() {
+        assert_eq!((5, -2), Grid::decompose(0.05));
+        assert_eq!((1, 3), Grid::decompose(1000.0));
+        // log10 lands just below 0, so the mantissa rounds up to 10
+        assert_eq!((1, 0), Grid::decompose(0.9999999999));
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::LinkedList;
+use bevy::{prelude::*, render::pipeline::PrimitiveTopology};
+use voronoice::Voronoi;
+
+use super::{
+    utils,
+    ColorBundle,
+    Object,
+    voronoi_mesh_generator::VoronoiMeshGenerator,
+};
+
+/// One layer per numpad key.
+pub const MAX_SITE_LAYERS: usize = 9;
+
+pub const LAYER_KEYS: [KeyCode; MAX_SITE_LAYERS] = [
+    KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3,
+    KeyCode::Numpad4, KeyCode::Numpad5, KeyCode::Numpad6,
+    KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+];
+
+fn color_blue(_i: usize) -> Color {
+    Color::rgb(0.2, 0.6, 1.0)
+}
+
+fn color_green(_i: usize) -> Color {
+    Color::rgb(0.3, 1.0, 0.3)
+}
+
+fn color_orange(_i: usize) -> Color {
+    Color::rgb(1.0, 0.6, 0.1)
+}
+
+fn color_purple(_i: usize) -> Color {
+    Color::rgb(0.9, 0.3, 1.0)
+}
+
+const LAYER_COLORINGS: [fn(usize) -> Color; 4] = [color_blue, color_green, color_orange, color_purple];
+
+/// A diagram with its own undo history. Inactive layers are drawn over the active one.
+pub struct SiteLayer {
+    pub name: &'static str,
+    /// Diagram of the layer. The active layer's diagram and history live in `State` instead, so this is `None` for it.
+    pub voronoi: Option<Voronoi>,
+    pub undo_list: LinkedList<Voronoi>,
+    pub forward_list: LinkedList<Voronoi>,
+    pub visible: bool,
+    coloring: fn(usize) -> Color,
+}
+
+impl SiteLayer {
+    /// Creates the `index`-th layer, which gets its own color.
+    pub fn new(name: &'static str, voronoi: Option<Voronoi>, index: usize) -> Self {
+        SiteLayer {
+            name,
+            voronoi,
+            undo_list: LinkedList::new(),
+            forward_list: LinkedList::new(),
+            visible: true,
+            coloring: LAYER_COLORINGS[index % LAYER_COLORINGS.len()],
+        }
+    }
+
+    /// Describes the `index`-th layer, given its diagram since the active one is not stored in the layer.
+    pub fn describe(&self, index: usize, voronoi: Option<&Voronoi>, active: bool) -> String {
+        format!("{}{}:{}({}){}",
+            if active { "*" } else { "" },
+            index + 1,
+            self.name,
+            voronoi.map_or(0, |v| v.sites().len()),
+            if self.visible { "" } else { " hidden" })
+    }
+
+    /// Spawns the cell edges and sites of the layer, as the `index`-th overlay drawn at `height`.
+    pub fn spawn(&self, commands: &mut Commands, meshes: &mut Assets<Mesh>, height: f32, index: usize, opacity: f32) {
+        let voronoi = if let Some(v) = self.voronoi.as_ref() {
+            v
+        } else {
+            return;
+        };
+        let edges = VoronoiMeshGenerator { voronoi, coloring: self.coloring, topology: PrimitiveTopology::LineList };
+        let sites = VoronoiMeshGenerator { voronoi, coloring: self.coloring, topology: PrimitiveTopology::PointList };
+        // overlays are stacked in the order they were added
+        let transform = Transform::from_translation(Vec3::new(0.0, height + 0.00005 * index as f32, 0.0));
+
+        let mut color = (self.coloring)(0);
+        color.set_a(opacity);
+
+        for mut mesh in vec![edges.build_voronoi_mesh(), sites.build_delauney_mesh()] {
+            utils::set_mesh_color(&mut mesh, color);
+            commands
+                .spawn_bundle(ColorBundle {
+                    mesh: meshes.add(mesh),
+                    transform,
+                    ..if opacity < 1.0 { ColorBundle::translucent() } else { ColorBundle::default() }
+                })
+                .insert(Object);
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use std::{collections::LinkedList, time::Instant};
-use bevy::{prelude::*, render::{camera::{Camera, PerspectiveProjection}, mesh::Indices, pipeline::PrimitiveTopology}};
-use rand::Rng;
+use std::{collections::LinkedList, panic::{self, AssertUnwindSafe}, time::Instant};
+use bevy::{prelude::*, asset::LoadState, ecs::component::Component, render::{camera::Camera, mesh::Indices, pipeline::PrimitiveTopology}, window::ReceivedCharacter};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use voronoice::*;
 
 mod pipeline;
@@ -8,22 +8,66 @@ mod into_triangle_list;
 mod utils;
 mod voronoi_mesh_generator;
 mod voronoi_cell_mesh_generator;
+mod brush;
+mod grid;
+mod prompt;
+mod symmetry;
+mod measure;
+mod preview;
+mod camera;
+mod minimap;
+mod split;
+mod layers;
+mod diff;
+mod render_layers;
+mod site_generators;
+mod density;
+mod region;
+mod curve;
 
 use pipeline::*;
-use voronoi_mesh_generator::*;
+use brush::Brush;
+use grid::Grid;
+use prompt::{PromptKind, TextPrompt, parse_point};
+use symmetry::Symmetry;
+use measure::Measurement;
+use camera::{CameraView, MainCamera};
+use split::SplitView;
+use layers::{SiteLayer, LAYER_KEYS, MAX_SITE_LAYERS};
+use render_layers::{LayerKind, RenderLayers, RENDER_LAYER_KEYS};
+use site_generators::{GeneratorInput, GeneratorRegistry, IMAGE_GENERATOR, RANDOM_GENERATOR, STRESS_GENERATOR};
+use density::DensityMap;
+use region::Region;
+use curve::{CurveKind, CurveTool};
 use voronoi_cell_mesh_generator::VoronoiCellMeshGenerator;
 
-const STRING_UI_COUNT: usize = 8;
+const STRING_UI_COUNT: usize = 19;
+/// Height of a status line. All the lines, the last of which is the prompt, must fit in the default 720 pixel high window.
+const UI_LINE_HEIGHT: f32 = 32.0;
+const UI_FONT_SIZE: f32 = 20.0;
+/// Title line followed by one line per render layer
+const RENDER_LAYER_UI_COUNT: usize = 9;
 
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
         .add_plugin(VertexColorPlugin)
         .insert_resource(ClearColor(Color::rgb(0., 0., 0.))) //background
+        .init_resource::<State>()
         .add_startup_system(setup.system())
-        .add_system(calculate_mouse_world_coords.system())
+        .add_startup_system(minimap::setup_minimap.system())
+        .add_startup_system(split::setup_split_cursor.system())
+        .add_system(camera::control_camera.system().label(camera::CAMERA_SYSTEM))
+        .add_system(calculate_mouse_world_coords.system().after(camera::CAMERA_SYSTEM))
         .add_system(handle_input.system())
-        .add_system(move_camera.system())
+        .add_system(measure::update_measurement_overlay.system())
+        .add_system(measure::position_world_labels.system())
+        .add_system(preview::update_insert_preview.system())
+        .add_system(minimap::update_minimap.system())
+        .add_system(split::update_split_cursor.system())
+        .add_system(diff::update_diff_overlay.system())
+        .add_system(region::update_region_overlay.system())
+        .add_system(curve::update_curve_overlay.system())
         .run();
 }
 
@@ -35,52 +79,23 @@ fn color_red(_i: usize) -> Color {
     Color::RED
 }
 
-struct VoronoiMeshOptions {
-    voronoi_topoloy: PrimitiveTopology,
-    delauney_topoloy: PrimitiveTopology,
-}
-
-impl Default for VoronoiMeshOptions {
-    fn default() -> Self {
-        VoronoiMeshOptions {
-            voronoi_topoloy: PrimitiveTopology::LineList,
-            delauney_topoloy: PrimitiveTopology::LineList
-        }
-    }
-}
-
 struct Object;
 
-fn spawn_voronoi(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, voronoi: &Voronoi, options: &VoronoiMeshOptions) {
+fn spawn_voronoi(commands: &mut Commands, meshes: &mut Assets<Mesh>, voronoi: &Voronoi, render_layers: &RenderLayers, translation: Vec3) {
     let start = Instant::now();
-    let voronoi_generator = VoronoiMeshGenerator { voronoi: &voronoi, coloring: color_red, topology: options.voronoi_topoloy };
-    let triangle_generator = VoronoiMeshGenerator { voronoi: &voronoi, coloring: color_white, topology: options.delauney_topoloy };
-
-    commands
-        .spawn_bundle(
-            ColorBundle {
-                mesh: meshes.add(voronoi_generator.build_voronoi_mesh()),
-                transform: Transform::from_translation(Vec3::new(
-                    0.0,
-                    0.0,
-                    0.0,
-                )),
-                ..Default::default()
-            })
-            .insert(Object);
 
-    commands
-        .spawn_bundle(
-            ColorBundle {
-                    mesh: meshes.add(triangle_generator.build_delauney_mesh()),
-                    transform: Transform::from_translation(Vec3::new(
-                        0.0,
-                        0.0,
-                        0.0,
-                    )),
-                    ..Default::default()
-        })
-        .insert(Object);
+    for layer in render_layers.iter().filter(|l| l.visible) {
+        if let Some(mesh) = layer.build_mesh(voronoi) {
+            commands
+                .spawn_bundle(
+                    ColorBundle {
+                        mesh: meshes.add(mesh),
+                        transform: Transform::from_translation(translation + Vec3::Y * render_layers.height(layer.kind)),
+                        ..layer.bundle()
+                    })
+                .insert(Object);
+        }
+    }
 
     println!("Generated new voronoi meshes in {:?}", start.elapsed());
 }
@@ -108,19 +123,22 @@ fn spawn_voronoi_cell(commands: &mut Commands, meshes: &mut ResMut<Assets<Mesh>>
         .insert(Object);
 }
 
-const CAMERA_Y: f32 = 6.0;
 struct StatusDisplay;
 
-fn add_display_lines(commands: &mut ChildBuilder, font: Handle<Font>) {
+struct RenderLayerDisplay;
+
+struct BrushCursor;
+
+fn add_display_lines<T: Component>(commands: &mut ChildBuilder, font: Handle<Font>, marker: T) {
     commands.spawn_bundle(TextBundle {
         style: Style {
-            size: Size::new(Val::Px(500.0), Val::Px(40.0)),
+            size: Size::new(Val::Px(500.0), Val::Px(UI_LINE_HEIGHT)),
             ..Default::default()
         },
         text: Text::with_section(
             "",
             TextStyle {
-                font_size: 25.0,
+                font_size: UI_FONT_SIZE,
                 color: Color::WHITE,
                 font: font,
                 ..Default::default()
@@ -128,7 +146,7 @@ fn add_display_lines(commands: &mut ChildBuilder, font: Handle<Font>) {
             TextAlignment::default()),
         ..Default::default()
     })
-    .insert(StatusDisplay);
+    .insert(marker);
 }
 
 // right hand
@@ -140,11 +158,7 @@ fn setup(
     mut standard_materials: ResMut<Assets<StandardMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
 ) {
-    let camera_pos = Vec3::new(0.000001, CAMERA_Y, 0.0);
-    let mut camera_t = Transform::from_translation(camera_pos)
-        .looking_at(Vec3::default(), Vec3::Y);
-    // roll camera so Z point up, and X right
-    camera_t.rotate(Quat::from_rotation_ypr(0.0, 0.0, 180f32.to_radians()));
+    let camera_view = CameraView::default();
 
     let font_handle = asset_server.load("fonts/FiraSans-Bold.ttf");
     let font_handle2 = font_handle.clone();
@@ -160,7 +174,28 @@ fn setup(
     }).with_children(|mut parent| {
         let font = font_handle2;
         for _i in 0..STRING_UI_COUNT {
-            add_display_lines(&mut parent, font.clone());
+            add_display_lines(&mut parent, font.clone(), StatusDisplay);
+        }
+    });
+
+    // render layers are listed in a second column
+    let font = font_handle.clone();
+    commands.spawn_bundle(NodeBundle{
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(0.0),
+                right: Val::Px(0.0),
+                ..Default::default()
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            ..Default::default()
+        },
+        material: color_materials.add(Color::NONE.into()),
+        ..Default::default()
+    }).with_children(|mut parent| {
+        for _i in 0..RENDER_LAYER_UI_COUNT {
+            add_display_lines(&mut parent, font.clone(), RenderLayerDisplay);
         }
     });
 
@@ -169,9 +204,11 @@ fn setup(
 
     // camera
     commands.spawn_bundle(PerspectiveCameraBundle {
-            transform: camera_t,
+            transform: camera_view.transform(),
             ..Default::default()
-        });
+        })
+        .insert(camera_view)
+        .insert(MainCamera);
 
     commands.spawn_bundle(TextBundle {
             style: Style {
@@ -192,27 +229,26 @@ fn setup(
         .insert(Mouse::default());
 
     commands.spawn_bundle(ColorBundle {
-            mesh: meshes.add(get_bounding_box(2.0)),
+            mesh: meshes.add(get_circle(32)),
+            visible: Visible { is_visible: false, ..Default::default() },
             ..Default::default()
         })
-        .insert(BoundingBox::new_centered_square(43.0)) // this value does not matter
-        .insert(Object);
+        .insert(BrushCursor);
 }
 
-fn get_bounding_box(size: f32) -> Mesh {
-    let edge = size / 2.0;
-    let pos = vec![
-        [-edge, 0.0, -edge], // bottom left
-        [-edge, 0.0, edge], // bottom right
-        [edge, 0.0, edge], // top right
-        [edge, 0.0, -edge], // top left
-    ];
+/// Unit circle outline on the XZ plane.
+fn get_circle(segments: u32) -> Mesh {
+    let pos: Vec<[f32; 3]> = (0..segments)
+        .map(|i| (i as f32 * 360.0 / segments as f32).to_radians())
+        .map(|a| [a.cos(), 0.0, a.sin()])
+        .collect();
 
     let mut m = Mesh::new(PrimitiveTopology::LineStrip);
     m.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; pos.len()]);
     m.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; pos.len()]);
+    m.set_attribute("Vertex_Color", vec![utils::color_to_f32_vec(Color::WHITE); pos.len()]);
     m.set_attribute(Mesh::ATTRIBUTE_POSITION, pos);
-    m.set_indices(Some(Indices::U32(vec![0, 1, 2, 3, 0])));
+    m.set_indices(Some(Indices::U32((0..segments).chain(std::iter::once(0)).collect())));
 
     m
 }
@@ -225,99 +261,118 @@ fn get_closest_site(voronoi: &Voronoi, pos: Vec3) -> Option<(usize, f32)> {
 #[derive(Default)]
 #[derive(Debug)]
 struct Mouse {
-    world_pos : Vec3
+    world_pos : Vec3,
+    /// False when the cursor is outside the window or does not point at the diagram plane; `world_pos` then holds the last position on the plane
+    on_plane: bool,
+    /// Translation of the split view pane under the cursor; `world_pos` is relative to it
+    pane_offset: Vec3,
 }
 const MOUSE_TEXT_OFFSET: f32 = 15.0;
-fn calculate_mouse_world_coords(mut mouse_query: Query<(&mut Mouse, &mut Text, &mut Style)>, query: Query<(&Transform, &Camera), With<PerspectiveProjection>>, windows: Res<Windows>) {
+fn calculate_mouse_world_coords(state: Res<State>, mut mouse_query: Query<(&mut Mouse, &mut Text, &mut Style)>, query: Query<(&Transform, &Camera), With<MainCamera>>, windows: Res<Windows>) {
     let (mut mouse,  mut text, mut text_style) = mouse_query.iter_mut().next().unwrap();
 
-    for ((camera_transform, camera), window) in query.iter().zip(windows.iter()) {
-        let screen_size = Vec2::from([window.width() as f32, window.height() as f32]);
-        let cursor_screen_pos = window.cursor_position().unwrap_or(Vec2::ZERO);
-
-        // normalize cursor coords (-1 to 1)
-        // extend z to 0 to project point on near plane when transforming it to world coords
-        let cursor_pos_normalized = (2.0 * (cursor_screen_pos / screen_size) - Vec2::new(1.0, 1.0)).extend(0.0);
-        let view_matrix = camera_transform.compute_matrix();
-        let screen_normal_coords_to_world = view_matrix * camera.projection_matrix.inverse();
-
-        let cursor_world_pos = screen_normal_coords_to_world.transform_point3(cursor_pos_normalized);
-        let ray: Vec3 = cursor_world_pos - camera_transform.translation;
-
-        // FIXME I put this together to debug voronoi generator
-        // this is assuming camera looking down Y
-        // genealize this ray-plane intersection logic based on the camera forward vector
-        let mut world_pos = -camera_transform.translation.y * (ray / ray.y);
-        world_pos.y = 0.0;
-        mouse.world_pos = world_pos;
-        text.sections[0].value = format!("({:.2}, {:.2})", mouse.world_pos.z, mouse.world_pos.x);
-
-        text_style.position.left = Val::Px(cursor_screen_pos.x + MOUSE_TEXT_OFFSET);
-        text_style.position.top = Val::Px(window.height() - cursor_screen_pos.y + MOUSE_TEXT_OFFSET);
-    }
-}
-
-fn move_camera(input: Res<Input<KeyCode>>, mut camera_query: Query<&mut Transform, (With<Camera>, With<PerspectiveProjection>)>) {
-    if input.pressed(KeyCode::W) {
-        for mut t in camera_query.iter_mut() {
-            let y_move = 0.1f32.min((t.translation.y - 0.7).powf(10.0));
-            t.translation.y -= y_move;
-        }
-    } else if input.pressed(KeyCode::S) {
-        for mut t in camera_query.iter_mut() {
-            t.translation.y += 0.1;
-        }
-    } else if input.pressed(KeyCode::R) {
-        for mut t in camera_query.iter_mut() {
-            t.translation.y = CAMERA_Y;
+    if let (Some((camera_transform, camera)), Some(window)) = (query.iter().next(), windows.get_primary()) {
+        // the minimap covers the diagram under it
+        let cursor = window.cursor_position()
+            .filter(|cursor_screen_pos| !minimap::contains(window, *cursor_screen_pos))
+            .and_then(|cursor_screen_pos| camera::screen_to_plane(cursor_screen_pos, window, camera, camera_transform)
+                .map(|world_pos| (cursor_screen_pos, world_pos)));
+
+        if let Some((cursor_screen_pos, world_pos)) = cursor {
+            // both split view panes map back to the same diagram coordinates
+            mouse.pane_offset = state.split.as_ref().map_or(Vec3::ZERO, |split| split.pane_offset(world_pos));
+            mouse.world_pos = world_pos - mouse.pane_offset;
+            mouse.on_plane = true;
+            text.sections[0].value = format!("({:.2}, {:.2})", mouse.world_pos.z, mouse.world_pos.x);
+
+            text_style.position.left = Val::Px(cursor_screen_pos.x + MOUSE_TEXT_OFFSET);
+            text_style.position.top = Val::Px(window.height() - cursor_screen_pos.y + MOUSE_TEXT_OFFSET);
+        } else {
+            mouse.on_plane = false;
+            text.sections[0].value.clear();
         }
     }
 }
 
-fn create_random_sites(size: usize, bounding_box: &BoundingBox) -> Vec<Point> {
-    let mut rng = rand::thread_rng();
-    let x_range = rand::distributions::Uniform::new(-bounding_box.width(), bounding_box.width());
-    let y_range = rand::distributions::Uniform::new(-bounding_box.height(), bounding_box.height());
-
-    (0..size)
-        .map(|_| Point { x: rng.sample(x_range), y: rng.sample(y_range) })
-        .collect()
+/// Builds the diagram, reporting a panic inside the builder as a failed build so degenerate inputs can be inspected without crashing.
+fn build_voronoi(builder: VoronoiBuilder) -> Option<Voronoi> {
+    panic::catch_unwind(AssertUnwindSafe(|| builder.build())).unwrap_or_else(|_| {
+        println!("VoronoiBuilder panicked, diagram not built");
+        None
+    })
 }
 
-#[derive(Debug)]
-enum SiteType {
-    Random,
-    Circle,
-    Square
-}
-impl Default for SiteType {
-    fn default() -> Self {
-        SiteType::Random
+fn next_clip_behavior(clip_behavior: ClipBehavior) -> ClipBehavior {
+    match clip_behavior {
+        ClipBehavior::Clip => ClipBehavior::None,
+        ClipBehavior::None => ClipBehavior::RemoveSitesOutsideBoundingBoxOnly,
+        ClipBehavior::RemoveSitesOutsideBoundingBoxOnly => ClipBehavior::Clip
     }
 }
+
 #[derive(Default)]
 struct State {
-    voronoi_opts: VoronoiMeshOptions,
+    render_layers: RenderLayers,
     voronoi: Option<Voronoi>,
     clip_behavior: ClipBehavior,
     size: usize,
     undo_list: LinkedList<Voronoi>,
     forward_list: LinkedList<Voronoi>,
     bounding_box: BoundingBox,
-    site_type: SiteType,
-    show_boundingbox: bool,
+    generators: GeneratorRegistry,
+    /// Seed of the random number generator used to generate sites
+    seed: u64,
+    /// Random edits (brush sprays, curve jitter) made since the last generation, so each one draws from its own seeded generator
+    edits: u64,
+    /// Density image being loaded, for the image site generator
+    density_image: Option<Handle<Texture>>,
+    /// Density of the last loaded image, kept when the diagram is cleared
+    density_map: Option<DensityMap>,
     path_start_site: Option<usize>,
     path_end_site: Option<usize>,
+    brush: Brush,
+    /// Whether a brush stroke is being painted. The diagram from before the stroke is at the front of the undo list.
+    stroke_active: bool,
+    grid: Grid,
+    prompt: Option<TextPrompt>,
+    /// Site picked with the cell inspector (middle click)
+    selected_site: Option<usize>,
+    symmetry: Symmetry,
+    /// Points being measured, when in measurement mode
+    measurement: Option<Measurement>,
+    /// Polygon sites are generated in instead of the whole bounding box, kept when the diagram is cleared
+    region: Option<Region>,
+    /// Vertices clicked so far, when in region drawing mode
+    region_drawing: Option<Vec<Point>>,
+    /// Whether sites are also placed along the region boundary
+    region_boundary: bool,
+    /// Polyline or Bezier curve being drawn, when the curve tool is active
+    curve: Option<CurveTool>,
+    /// Incremented every time the diagram meshes are respawned, so overlays know when to redraw
+    generation: u64,
+    /// Saved camera views, kept when the diagram is cleared
+    camera_bookmarks: [Option<CameraView>; 9],
+    /// Same sites built with other options, shown next to the diagram
+    split: Option<SplitView>,
+    /// Diagrams drawn over the active one, kept when the diagram is cleared. Empty until a second layer is added.
+    /// The active layer's diagram and history are `voronoi`, `undo_list` and `forward_list`.
+    site_layers: Vec<SiteLayer>,
+    active_layer: usize,
+    /// Whether cells changed since the head of the undo list are highlighted
+    diff: bool,
 }
 impl State {
+    /// Makes `v` the current diagram and pushes the previous one to the undo list.
+    /// A failed build (`None`) keeps the current diagram, so a bad input never leaves the app without one.
     fn replace(&mut self, v: Option<Voronoi>) -> Option<&Voronoi> {
-        let old = if let Some(new) = v  {
-            self.voronoi.replace(new)
+        let new = if let Some(new) = v {
+            new
         } else {
-            self.voronoi.take()
+            println!("Diagram could not be built, keeping the current one");
+            return None;
         };
 
-        if let Some(old) = old {
+        if let Some(old) = self.voronoi.replace(new) {
             self.undo_list.push_front(old);
 
             self.undo_list.front()
@@ -326,7 +381,23 @@ impl State {
         }
     }
 
+    /// Ends the measurement, region drawing and curve tools. Clicks go to one tool at a time, so starting one ends the others.
+    fn end_click_tools(&mut self) {
+        self.measurement = None;
+        self.region_drawing = None;
+        self.curve = None;
+    }
+
+    /// Forgets the selected and path sites, for when site indices may have shifted.
+    fn clear_site_indices(&mut self) {
+        self.selected_site = None;
+        self.path_start_site = None;
+        self.path_end_site = None;
+    }
+
     fn undo(&mut self) -> Option<&Voronoi> {
+        // site indices are not kept across diagrams
+        self.clear_site_indices();
         if let Some(prev) = self.undo_list.pop_front() {
             if let Some(curr) = self.voronoi.replace(prev) {
                 self.forward_list.push_front(curr);
@@ -340,6 +411,7 @@ impl State {
     }
 
     fn undo_forward(&mut self) -> Option<&Voronoi> {
+        self.clear_site_indices();
         if let Some(prev) = self.forward_list.pop_front() {
             if let Some(curr) = self.voronoi.replace(prev) {
                 self.undo_list.push_front(curr);
@@ -357,9 +429,11 @@ impl State {
         self.undo_list.clear();
         self.forward_list.clear();
         self.bounding_box = BoundingBox::new_centered_square(2.0);
-        self.show_boundingbox = false;
-        self.path_start_site = None;
-        self.path_end_site = None;
+        self.clear_site_indices();
+        self.stroke_active = false;
+        self.prompt = None;
+        // regenerating from scratch gives a new random diagram
+        self.seed = rand::thread_rng().gen();
     }
 
     fn new_builder(&self) -> VoronoiBuilder {
@@ -371,61 +445,301 @@ impl State {
     fn new_voronoi(&mut self, size: usize) {
         let start = Instant::now();
         self.size = size;
-        let mut builder = self.new_builder();
-
-        builder = match self.site_type {
-            SiteType::Random => builder.set_sites(create_random_sites(size, &self.bounding_box)),
-            SiteType::Circle => builder.generate_circle_sites(self.size, 1.0),
-            SiteType::Square => builder.generate_square_sites(self.size),
+        self.edits = 0;
+        self.clear_site_indices();
+        // a region being drawn is replaced by the one the diagram was generated in
+        self.region_drawing = None;
+
+        // every generation starts from the seed, so the same seed and settings give the same diagram
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let input = GeneratorInput {
+            bounding_box: &self.bounding_box,
+            size,
+            density_map: self.density_map.as_ref(),
+        };
+        let sites = match self.region.as_ref() {
+            Some(region) => region.generate(self.generators.selected(), &input, &mut rng, self.region_boundary),
+            None => self.generators.selected().generate(&input, &mut rng),
         };
 
-        let voronoi = builder.build();
-        println!("Generated new voronoi of size {} in {:?}", self.size, start.elapsed());
+        let voronoi = build_voronoi(self.new_builder().set_sites(sites));
+        println!("Generated new voronoi of size {} with seed {} in {:?}", self.size, self.seed, start.elapsed());
+
+        // without a diagram to keep, a failed build would be retried every frame
+        if voronoi.is_none() && self.voronoi.is_none() && (self.generators.selected().name() != RANDOM_GENERATOR || self.region.is_some()) {
+            println!("Falling back to {} sites in the whole bounding box", RANDOM_GENERATOR);
+            self.generators.select(RANDOM_GENERATOR);
+            self.region = None;
+            self.new_voronoi(size);
+            return;
+        }
 
         self.replace(voronoi);
     }
 
     fn refresh(&mut self) {
+        // clipping may drop sites and shift indices
+        self.clear_site_indices();
         if let Some(v) = self.voronoi.as_ref() {
-            let vv = self.new_builder()
-                .set_sites(v.sites().clone())
-                .build();
+            let vv = build_voronoi(self.new_builder()
+                .set_sites(v.sites().clone()));
             self.replace(vv);
         }
     }
 
     fn add_site_to_voronoi(&mut self, site: Point) {
-        let mut sites = self.voronoi.as_ref().unwrap().sites().clone();
-        sites.push(site);
+        self.add_sites_to_voronoi(vec![site]);
+    }
+
+    fn add_sites_to_voronoi(&mut self, new_sites: Vec<Point>) {
+        let mut sites = if let Some(v) = self.voronoi.as_ref() {
+            v.sites().clone()
+        } else {
+            return;
+        };
+        sites.extend(new_sites);
 
-        let v = self.new_builder()
-            .set_sites(sites)
-            .build();
+        let v = build_voronoi(self.new_builder()
+            .set_sites(sites));
         self.replace(v);
     }
 
     fn remove_site_to_voronoi(&mut self, site_index: usize) {
-        let mut sites = self.voronoi.as_ref().unwrap().sites().clone();
+        let mut sites = match self.voronoi.as_ref() {
+            Some(v) if site_index < v.sites().len() => v.sites().clone(),
+            _ => return,
+        };
         sites.remove(site_index);
 
-        let v = self.new_builder()
-            .set_sites(sites)
-            .build();
+        let v = build_voronoi(self.new_builder()
+            .set_sites(sites));
+        // a failed build keeps the current diagram and its indices
+        if self.replace(v).is_none() {
+            return;
+        }
+
+        // indices after the removed site shift down
+        let remap = |site: Option<usize>| match site {
+            Some(s) if s == site_index => None,
+            Some(s) if s > site_index => Some(s - 1),
+            s => s,
+        };
+        self.selected_site = remap(self.selected_site);
+        self.path_start_site = remap(self.path_start_site);
+        self.path_end_site = remap(self.path_end_site);
+    }
+
+    fn move_site(&mut self, site_index: usize, site: Point) {
+        let mut sites = if let Some(v) = self.voronoi.as_ref() {
+            v.sites().clone()
+        } else {
+            return;
+        };
+        if let Some(s) = sites.get_mut(site_index) {
+            *s = site;
+        } else {
+            return;
+        }
+
+        let v = build_voronoi(self.new_builder()
+            .set_sites(sites));
         self.replace(v);
     }
+
+    /// Index of the site exactly at `p`, if there is one.
+    fn find_site(&self, p: &Point) -> Option<usize> {
+        self.voronoi.as_ref()?.sites().iter().position(|s| s.x == p.x && s.y == p.y)
+    }
+
+    /// Where a site placed with the mouse goes, snapped to the grid if it is enabled.
+    fn placement_point(&self, world_pos: Vec3) -> Point {
+        let point = Point { x: world_pos.z as f64, y: world_pos.x as f64 };
+        if self.grid.enabled {
+            self.grid.snap(&point, &self.bounding_box)
+        } else {
+            point
+        }
+    }
+
+    /// Sites a click at `placement` adds: its symmetric images, except those extremely close to an existing site as they degenerate the triangulation.
+    fn insertion_sites(&self, placement: &Point) -> Vec<Point> {
+        self.symmetry.images(placement, self.bounding_box.center())
+            .into_iter()
+            .filter(|p| {
+                let closest_site = self.voronoi.as_ref()
+                    .and_then(|v| get_closest_site(v, Vec3::from(utils::point_to_f32_vec(p))));
+                closest_site.is_none() || closest_site.unwrap().1 > 0.001
+            })
+            .collect()
+    }
+
+    /// Sprays (or erases) sites with the brush around `center`. Returns true if the diagram changed.
+    /// All changes made until [`State::end_stroke`] is called are undone as a single entry.
+    fn paint(&mut self, center: Point, erase: bool) -> bool {
+        let mut sites = if let Some(v) = self.voronoi.as_ref() {
+            v.sites().clone()
+        } else {
+            return false;
+        };
+
+        let changed = if erase {
+            self.brush.erase(&center, &mut sites)
+        } else {
+            let mut rng = self.edit_rng();
+            self.brush.spray(&center, &mut sites, &mut rng)
+        };
+
+        // don't let it go below 3 as it won't triangulate
+        if changed == 0 || sites.len() < 3 {
+            return false;
+        }
+
+        let v = if let Some(v) = build_voronoi(self.new_builder().set_sites(sites)) {
+            v
+        } else {
+            return false;
+        };
+
+        if erase {
+            // site indices shift when sites are removed
+            self.clear_site_indices();
+        }
+
+        if self.stroke_active {
+            // diagram from before the stroke is already in the undo list
+            self.voronoi = Some(v);
+        } else {
+            self.stroke_active = true;
+            self.replace(Some(v));
+        }
+
+        true
+    }
+
+    fn end_stroke(&mut self) {
+        self.stroke_active = false;
+    }
+
+    /// Random number generator for the next random edit, derived from the seed, so the same seed and edits give the same diagram.
+    fn edit_rng(&mut self) -> StdRng {
+        self.edits += 1;
+        StdRng::seed_from_u64(self.seed.wrapping_add(self.edits))
+    }
+
+    /// Places sites along the curve being drawn with the given settings, and starts a new curve.
+    fn place_curve_sites(&mut self, count: usize, jitter: f64) {
+        if self.voronoi.is_none() {
+            return;
+        }
+
+        let mut rng = self.edit_rng();
+        let sites = if let Some(curve) = self.curve.as_mut() {
+            curve.count = count;
+            curve.jitter = jitter;
+            let sites = curve.sites(&mut rng);
+            curve.points.clear();
+            sites
+        } else {
+            return;
+        };
+
+        self.add_sites_to_voronoi(sites);
+    }
+
+    /// Adds a diagram of `sites`, built with the current options, as a new overlay layer.
+    fn add_site_layer(&mut self, name: &'static str, sites: Option<Vec<Point>>) -> bool {
+        // the current diagram becomes the first layer
+        let first = usize::from(self.site_layers.is_empty());
+        if self.site_layers.len() + first >= MAX_SITE_LAYERS {
+            println!("No more than {} layers", MAX_SITE_LAYERS);
+            return false;
+        }
+
+        let v = sites.and_then(|sites| build_voronoi(self.new_builder().set_sites(sites)));
+        if let Some(v) = v {
+            if first == 1 {
+                self.site_layers.push(SiteLayer::new("main", None, 0));
+                self.active_layer = 0;
+            }
+            let index = self.site_layers.len();
+            self.site_layers.push(SiteLayer::new(name, Some(v), index));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the last layer, unless it is the active one.
+    fn remove_site_layer(&mut self) -> bool {
+        match self.site_layers.len() {
+            0 => false,
+            // only the active layer is left
+            1 => {
+                self.site_layers.clear();
+                true
+            },
+            len if len - 1 == self.active_layer => {
+                println!("Cannot remove the active layer");
+                false
+            },
+            _ => self.site_layers.pop().is_some(),
+        }
+    }
+
+    /// Makes the `index`-th layer the one being edited, parking the current diagram and history in its layer.
+    fn activate_site_layer(&mut self, index: usize) -> bool {
+        if index == self.active_layer || index >= self.site_layers.len() {
+            return false;
+        }
+
+        let active = &mut self.site_layers[self.active_layer];
+        active.voronoi = self.voronoi.take();
+        active.undo_list = std::mem::take(&mut self.undo_list);
+        active.forward_list = std::mem::take(&mut self.forward_list);
+
+        let layer = &mut self.site_layers[index];
+        self.voronoi = layer.voronoi.take();
+        self.undo_list = std::mem::take(&mut layer.undo_list);
+        self.forward_list = std::mem::take(&mut layer.forward_list);
+        self.active_layer = index;
+
+        // site indices and strokes belong to the previous diagram
+        self.clear_site_indices();
+        self.stroke_active = false;
+        true
+    }
+
+    /// Whether the diagram is drawn, which is always the case until layers are added.
+    fn active_site_layer_visible(&self) -> bool {
+        self.site_layers.get(self.active_layer).map_or(true, |l| l.visible)
+    }
+
+    /// Diagram of the `index`-th layer, which is the current one for the active layer.
+    fn site_layer_voronoi(&self, index: usize) -> Option<&Voronoi> {
+        if index == self.active_layer {
+            self.voronoi.as_ref()
+        } else {
+            self.site_layers.get(index).and_then(|l| l.voronoi.as_ref())
+        }
+    }
 }
 
 fn handle_input(
-    mut state: Local<State>,
+    mut state: ResMut<State>,
     input: Res<Input<KeyCode>>,
     mouse_button_input: Res<Input<MouseButton>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut commands: Commands,
     query: Query<Entity, With<Object>>,
-    mut query_text: Query<&mut Text, With<StatusDisplay>>,
-    mut query_box: Query<(&mut Transform, &mut Visible), With<BoundingBox>>,
+    mut query_text: Query<&mut Text, (With<StatusDisplay>, Without<RenderLayerDisplay>)>,
+    mut query_layer_text: Query<&mut Text, With<RenderLayerDisplay>>,
+    mut query_brush: Query<(&mut Transform, &mut Visible), With<BrushCursor>>,
     query_path: Query<Entity, With<DisplayVoronoiCell>>,
-    mouse_query: Query<&Mouse>) {
+    mouse_query: Query<&Mouse>,
+    mut char_input: EventReader<ReceivedCharacter>,
+    camera_query: Query<&CameraView>,
+    asset_server: Res<AssetServer>,
+    textures: Res<Assets<Texture>>) {
 
     let mut respawn = false;
 
@@ -437,6 +751,94 @@ fn handle_input(
         state.new_voronoi(20);
     }
 
+    // text input goes to the open prompt; keys are not handled as commands until it is closed
+    let typing = state.prompt.is_some();
+    let typed: Vec<char> = char_input.iter().map(|e| e.char).collect();
+    if let Some(mut prompt) = state.prompt.take() {
+        for c in typed.into_iter().filter(|c| !c.is_control()) {
+            prompt.push(c);
+        }
+
+        if input.just_pressed(KeyCode::Back) {
+            prompt.pop();
+        }
+
+        if input.just_pressed(KeyCode::Return) {
+            let accepted = match prompt.kind {
+                PromptKind::AddSite => parse_point(&prompt.text).map(|point| state.add_site_to_voronoi(point)).is_some(),
+                PromptKind::EditSite { x, y, .. } => parse_point(&prompt.text).map(|point| {
+                    // clicks can add or remove sites while the prompt is open, so the site is looked up again by position
+                    match state.find_site(&Point { x, y }) {
+                        Some(site) => state.move_site(site, point),
+                        None => println!("Site at ({}, {}) no longer exists", x, y),
+                    }
+                }).is_some(),
+                PromptKind::Seed => prompt.text.trim().parse::<u64>().ok().map(|seed| {
+                    state.seed = seed;
+                    let size = state.size;
+                    state.new_voronoi(size);
+                }).is_some(),
+                PromptKind::ImagePath => {
+                    // loads in the background, sites are generated once it is ready
+                    let path = prompt.text.trim();
+                    if !path.is_empty() {
+                        state.density_image = Some(asset_server.load(path));
+                    }
+                    !path.is_empty()
+                },
+                PromptKind::CurveSites => curve::parse_settings(&prompt.text).map(|(count, jitter)| state.place_curve_sites(count, jitter)).is_some(),
+                PromptKind::RegionPath => match Region::load(prompt.text.trim()) {
+                    Ok(region) => {
+                        println!("Loaded region with {}", region.describe());
+                        state.region = Some(region);
+                        let size = state.size;
+                        state.new_voronoi(size);
+                        true
+                    },
+                    Err(e) => {
+                        println!("{}", e);
+                        false
+                    },
+                },
+            };
+
+            if accepted {
+                respawn = true;
+            } else {
+                println!("Invalid input for {}: {}", prompt.label(), prompt.text);
+                state.prompt = Some(prompt);
+            }
+        } else if !input.just_pressed(KeyCode::Escape) {
+            state.prompt = Some(prompt);
+        }
+    }
+
+    if let Some(handle) = state.density_image.clone() {
+        match asset_server.get_load_state(&handle) {
+            LoadState::Loaded => {
+                state.density_image = None;
+                match textures.get(&handle).ok_or_else(|| "Density image is not loaded".to_string()).and_then(DensityMap::from_texture) {
+                    Ok(map) => {
+                        state.density_map = Some(map);
+                        state.generators.select(IMAGE_GENERATOR);
+                        let size = state.size;
+                        state.new_voronoi(size);
+                        respawn = true;
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
+            LoadState::Failed => {
+                state.density_image = None;
+                println!("Could not load density image");
+            },
+            _ => {},
+        }
+    }
+
+    let no_input = Input::<KeyCode>::default();
+    let input: &Input<KeyCode> = if typing { &no_input } else { &*input };
+
     if input.just_pressed(KeyCode::PageUp) || input.just_pressed(KeyCode::PageDown) {
         let increment = if input.pressed(KeyCode::LShift) {
             1.0
@@ -454,58 +856,138 @@ fn handle_input(
 
         respawn = true;
         state.refresh();
-    } else if input.just_pressed(KeyCode::V) {
-        state.show_boundingbox = !state.show_boundingbox;
     }
 
-    for (mut box_t, mut visible) in query_box.iter_mut() {
-        box_t.scale = Vec3::splat((state.bounding_box.width() / 2.0) as f32);
-        visible.is_visible = state.show_boundingbox;
+    // render layers, respawned with the same points
+    for (kind, key) in RENDER_LAYER_KEYS.iter() {
+        if input.just_pressed(*key) {
+            let layers = &mut state.render_layers;
+            match (input.pressed(KeyCode::LShift), input.pressed(KeyCode::LControl)) {
+                (false, false) => layers.toggle(*kind),
+                (true, false) => layers.raise(*kind),
+                (false, true) => layers.next_color(*kind),
+                (true, true) => layers.next_opacity(*kind),
+            }
+            respawn = true;
+        }
     }
 
-    // span new voronoi with new rendering but same points
-    if input.just_pressed(KeyCode::P) {
-        let options = &mut state.voronoi_opts;
-        options.voronoi_topoloy = match options.voronoi_topoloy {
-            PrimitiveTopology::TriangleList => PrimitiveTopology::LineList,
-            PrimitiveTopology::LineList => PrimitiveTopology::PointList,
-            _ => PrimitiveTopology::TriangleList,
-        };
-
-        respawn = true;
-    } else if input.just_pressed(KeyCode::O) {
-        let options = &mut state.voronoi_opts;
-        options.delauney_topoloy = match options.delauney_topoloy {
-            PrimitiveTopology::TriangleList => PrimitiveTopology::LineList,
-            PrimitiveTopology::LineList => PrimitiveTopology::PointList,
-            _ => PrimitiveTopology::TriangleList,
-        };
-
+    if state.split.is_some() && input.pressed(KeyCode::LShift) && input.just_pressed(KeyCode::L) {
+        if let Some(split) = state.split.as_mut() {
+            split.cycle_lloyd_iterations();
+        }
         respawn = true;
-    } else if input.pressed(KeyCode::L) {
+    } else if input.pressed(KeyCode::L) && !(state.split.is_some() && input.pressed(KeyCode::LShift)) {
         // run loyd relaxation
+        state.clear_site_indices();
         if let Some(existing_voronoi) = state.voronoi.as_ref() {
-            let builder: VoronoiBuilder = existing_voronoi.into();
-            state.replace(builder.set_lloyd_relaxation_iterations(1).build());
+            let density_map = state.density_map.as_ref().filter(|_| state.generators.selected().uses_density());
+            let relaxed = match density_map {
+                // stippling: sites move to the darkness-weighted centroid of their cells
+                Some(map) => {
+                    let cells: Vec<Vec<Point>> = existing_voronoi.iter_cells().map(|c| c.iter_vertices().cloned().collect()).collect();
+                    let sites = map.weighted_centroids(existing_voronoi.sites(), &cells, &state.bounding_box);
+                    build_voronoi(state.new_builder().set_sites(sites))
+                },
+                None => {
+                    let builder: VoronoiBuilder = existing_voronoi.into();
+                    build_voronoi(builder.set_lloyd_relaxation_iterations(1))
+                },
+            };
+            state.replace(relaxed);
             respawn = true;
         }
+    } else if state.split.is_some() && input.pressed(KeyCode::LShift) && input.just_pressed(KeyCode::C) {
+        // change hull behavior of the right pane
+        if let Some(split) = state.split.as_mut() {
+            split.cycle_clip_behavior();
+            println!("Split view clip behavior set to {:?}", split.clip_behavior);
+        }
+        respawn = true;
     } else if input.just_pressed(KeyCode::C) {
         // change hull behavior
-        state.clip_behavior = match state.clip_behavior {
-            ClipBehavior::Clip => ClipBehavior::None,
-            ClipBehavior::None => ClipBehavior::RemoveSitesOutsideBoundingBoxOnly,
-            ClipBehavior::RemoveSitesOutsideBoundingBoxOnly => ClipBehavior::Clip
-        };
+        state.clip_behavior = next_clip_behavior(state.clip_behavior);
         println!("Clip behavior set to {:?}", state.clip_behavior);
 
         state.refresh();
         respawn = true;
+    } else if input.just_pressed(KeyCode::H) {
+        state.split = match state.split {
+            Some(_) => None,
+            None => Some(SplitView::new(state.clip_behavior)),
+        };
+        respawn = true;
     }
 
     let mouse = mouse_query.iter().next().unwrap();
-    if mouse_button_input.just_pressed(MouseButton::Left) || mouse_button_input.just_pressed(MouseButton::Right) || mouse_button_input.just_pressed(MouseButton::Middle) {
+
+    // clicks only edit the diagram when the cursor is over it
+    let no_mouse_input = Input::<MouseButton>::default();
+    let mouse_button_input: &Input<MouseButton> = if mouse.on_plane { &*mouse_button_input } else { &no_mouse_input };
+
+    // brush radius and density
+    if input.just_pressed(KeyCode::RBracket) {
+        state.brush.radius *= 1.25;
+    } else if input.just_pressed(KeyCode::LBracket) {
+        state.brush.radius /= 1.25;
+    } else if input.just_pressed(KeyCode::Equals) {
+        state.brush.density *= 1.5;
+    } else if input.just_pressed(KeyCode::Minus) {
+        state.brush.density /= 1.5;
+    }
+
+    let brushing = input.pressed(KeyCode::Q) && mouse.on_plane;
+    for (mut brush_t, mut visible) in query_brush.iter_mut() {
+        brush_t.translation = mouse.world_pos + mouse.pane_offset;
+        brush_t.scale = Vec3::splat(state.brush.radius as f32);
+        visible.is_visible = brushing;
+    }
+
+    if brushing {
+        // Q + left drag sprays sites, Q + right drag erases them
+        let erase = mouse_button_input.pressed(MouseButton::Right);
+        if erase || mouse_button_input.pressed(MouseButton::Left) {
+            let center = Point { x: mouse.world_pos.z as f64, y: mouse.world_pos.x as f64 };
+            respawn |= state.paint(center, erase);
+        } else {
+            state.end_stroke();
+        }
+    } else if state.stroke_active {
+        state.end_stroke();
+    } else if state.measurement.is_some() && (mouse_button_input.just_pressed(MouseButton::Left) || mouse_button_input.just_pressed(MouseButton::Right)) {
+        // left click adds a point to measure, right click clears the measurement
+        let point = state.placement_point(mouse.world_pos);
+        if let Some(measurement) = state.measurement.as_mut() {
+            if mouse_button_input.just_pressed(MouseButton::Left) {
+                measurement.push(point);
+            } else {
+                measurement.clear();
+            }
+        }
+    } else if state.curve.is_some() && (mouse_button_input.just_pressed(MouseButton::Left) || mouse_button_input.just_pressed(MouseButton::Right)) {
+        // left click adds a curve point, right click removes the last one
+        let point = state.placement_point(mouse.world_pos);
+        if let Some(curve) = state.curve.as_mut() {
+            if mouse_button_input.just_pressed(MouseButton::Left) {
+                curve.points.push(point);
+            } else {
+                curve.points.pop();
+            }
+        }
+    } else if state.region_drawing.is_some() && (mouse_button_input.just_pressed(MouseButton::Left) || mouse_button_input.just_pressed(MouseButton::Right)) {
+        // left click adds a region vertex, right click removes the last one
+        let point = state.placement_point(mouse.world_pos);
+        if let Some(vertices) = state.region_drawing.as_mut() {
+            if mouse_button_input.just_pressed(MouseButton::Left) {
+                vertices.push(point);
+            } else {
+                vertices.pop();
+            }
+        }
+    } else if mouse_button_input.just_pressed(MouseButton::Left) || mouse_button_input.just_pressed(MouseButton::Right) || mouse_button_input.just_pressed(MouseButton::Middle) {
         // take sites and change based on type of click
         let point = Point { x: mouse.world_pos.z as f64, y: mouse.world_pos.x  as f64 };
+        let placement = state.placement_point(mouse.world_pos);
 
         let (closest_site, num_of_sites) = if let Some(voronoi) = state.voronoi.as_ref() {
             (get_closest_site(voronoi, mouse.world_pos), voronoi.sites().len())
@@ -520,10 +1002,11 @@ fn handle_input(
                     state.path_start_site = Some(site);
                 }
             } else {
-                // do not let adding points extremelly close as this degenerate triangulation
-                if closest_site.is_none() || closest_site.unwrap().1 > 0.001 {
-                    state.add_site_to_voronoi(point);
-                    info!("Site added: {:?}", mouse.world_pos);
+                let new_sites = state.insertion_sites(&placement);
+
+                if !new_sites.is_empty() {
+                    info!("Sites added: {:?}", new_sites);
+                    state.add_sites_to_voronoi(new_sites);
                     respawn = true;
                 }
             }
@@ -540,7 +1023,7 @@ fn handle_input(
                         }
 
                         // add new path
-                        if let Some(v) = state.voronoi.as_ref() {
+                        if let Some(v) = state.voronoi.as_ref().filter(|v| path_start_site < v.sites().len()) {
                             for s in v.cell(path_start_site).iter_path(point) {
                                 let cell = v.cell(s);
                                 spawn_voronoi_cell(&mut commands, &mut meshes, &cell);
@@ -558,10 +1041,11 @@ fn handle_input(
                     }
                 }
             }
-        } else if mouse_button_input.just_pressed(MouseButton::Middle) {
-            // print info for closest site
+        } else if mouse_button_input.just_pressed(MouseButton::Middle) && !input.pressed(camera::DRAG_MODIFIER) {
+            // print info for closest site; with the modifier the middle button drags the camera instead
             if let Some((site, dist)) = closest_site {
                 if dist < 0.2 {
+                    state.selected_site = Some(site);
                     if let Some(v) = state.voronoi.as_ref() {
                         let cell = v.cell(site);
                         println!("{:#?}", cell);
@@ -573,23 +1057,151 @@ fn handle_input(
         }
     }
 
-    // change number of points
+    // snapping grid
+    if input.just_pressed(KeyCode::J) {
+        state.grid.enabled = !state.grid.enabled;
+        respawn = true;
+    } else if input.just_pressed(KeyCode::Period) {
+        state.grid.increase_spacing();
+        respawn |= state.grid.enabled;
+    } else if input.just_pressed(KeyCode::Comma) {
+        state.grid.decrease_spacing();
+        respawn |= state.grid.enabled;
+    }
+
+    if input.just_pressed(KeyCode::D) {
+        state.diff = !state.diff;
+    }
+
+    if input.just_pressed(KeyCode::K) {
+        state.measurement = match state.measurement {
+            Some(_) => None,
+            None => {
+                state.end_click_tools();
+                Some(Measurement::default())
+            },
+        };
+    }
+
+    // cancels the measurement, region or curve being drawn
+    if input.just_pressed(KeyCode::Escape) {
+        state.end_click_tools();
+    }
+
+    // polygon region sites are generated in
+    if input.just_pressed(KeyCode::X) {
+        if input.pressed(KeyCode::LShift) {
+            state.prompt = Some(TextPrompt::new(PromptKind::RegionPath, String::new()));
+        } else if input.pressed(KeyCode::LControl) {
+            state.region = None;
+            state.region_drawing = None;
+            let size = state.size;
+            state.new_voronoi(size);
+            respawn = true;
+        } else if let Some(vertices) = state.region_drawing.take() {
+            // second press finishes the drawing
+            match Region::new(vertices) {
+                Ok(region) => {
+                    state.region = Some(region);
+                    let size = state.size;
+                    state.new_voronoi(size);
+                    respawn = true;
+                },
+                Err(e) => println!("{}", e),
+            }
+        } else {
+            state.end_click_tools();
+            state.region_drawing = Some(Vec::new());
+        }
+    } else if input.just_pressed(KeyCode::O) && state.region.is_some() {
+        state.region_boundary = !state.region_boundary;
+        let size = state.size;
+        state.new_voronoi(size);
+        respawn = true;
+    }
+
+    // sites along a polyline or Bezier curve
+    if input.just_pressed(KeyCode::U) {
+        if input.pressed(KeyCode::LControl) {
+            state.curve = None;
+        } else if input.pressed(KeyCode::LShift) {
+            match state.curve.as_mut() {
+                Some(curve) => curve.toggle_kind(),
+                None => {
+                    state.end_click_tools();
+                    state.curve = Some(CurveTool::new(CurveKind::Bezier));
+                },
+            }
+        } else {
+            match state.curve.as_ref() {
+                None => {
+                    state.end_click_tools();
+                    state.curve = Some(CurveTool::new(CurveKind::Polyline));
+                },
+                // second press asks how many sites to place along the curve
+                Some(curve) if curve.points.len() > 1 => {
+                    let text = format!("{}, {}", curve.count, curve.jitter);
+                    state.prompt = Some(TextPrompt::new(PromptKind::CurveSites, text));
+                },
+                Some(_) => state.curve = None,
+            }
+        }
+    }
+
+    // symmetric placement
+    if input.just_pressed(KeyCode::M) {
+        state.symmetry = state.symmetry.next_mirror();
+    } else if input.just_pressed(KeyCode::N) {
+        state.symmetry = if input.pressed(KeyCode::LShift) {
+            state.symmetry.decrease_fold()
+        } else {
+            state.symmetry.increase_fold()
+        };
+    }
+
+    // exact coordinates
+    if input.just_pressed(KeyCode::Return) && input.pressed(KeyCode::LShift) {
+        state.prompt = Some(TextPrompt::new(PromptKind::Seed, state.seed.to_string()));
+    } else if input.just_pressed(KeyCode::Return) {
+        let point = state.placement_point(mouse.world_pos);
+        state.prompt = Some(TextPrompt::new(PromptKind::AddSite, format!("{}, {}", point.x, point.y)));
+    } else if input.just_pressed(KeyCode::I) {
+        state.prompt = Some(TextPrompt::new(PromptKind::ImagePath, String::new()));
+    } else if input.just_pressed(KeyCode::E) {
+        if let Some(site) = state.selected_site {
+            if let Some(p) = state.voronoi.as_ref().and_then(|v| v.sites().get(site)) {
+                let (kind, text) = (PromptKind::EditSite { site, x: p.x, y: p.y }, format!("{}, {}", p.x, p.y));
+                state.prompt = Some(TextPrompt::new(kind, text));
+            }
+        }
+    }
+
+    // change number of points, arrows without control pan the camera
     let size = state.size;
     let change = if input.pressed(KeyCode::LShift) { 1000 } else { 100 };
-    if input.just_pressed(KeyCode::Up) {
+    if input.pressed(KeyCode::LControl) && input.just_pressed(KeyCode::Up) {
         respawn = true;
         state.new_voronoi(size + change);
-    } else if input.just_pressed(KeyCode::Down) {
+    } else if input.pressed(KeyCode::LControl) && input.just_pressed(KeyCode::Down) {
         respawn = true;
         state.new_voronoi((size as i64 - change as i64).max(120) as usize);
     } else if input.just_pressed(KeyCode::Home) {
-        state.site_type = match state.site_type {
-            SiteType::Circle => SiteType::Random,
-            SiteType::Random => SiteType::Square,
-            SiteType::Square => SiteType::Circle,
-        };
+        state.generators.select_next();
+        respawn = true;
+        state.new_voronoi(size);
+    } else if input.just_pressed(KeyCode::End) {
+        // degenerate inputs, the next case if they are already selected
+        state.generators.select_or_cycle(STRESS_GENERATOR);
         respawn = true;
         state.new_voronoi(size);
+    } else if input.just_pressed(KeyCode::Slash) {
+        // generator parameters
+        state.generators.next_param();
+    } else if input.just_pressed(KeyCode::Apostrophe) || input.just_pressed(KeyCode::Semicolon) {
+        if state.generators.adjust_param(input.just_pressed(KeyCode::Apostrophe)) {
+            respawn = true;
+            state.new_voronoi(size);
+        }
     }
 
     if input.pressed(KeyCode::LControl) {
@@ -604,6 +1216,30 @@ fn handle_input(
         }
     }
 
+    // overlay layers
+    if input.just_pressed(KeyCode::Insert) {
+        respawn |= if input.pressed(KeyCode::LShift) {
+            let sites = state.undo_list.front().map(|v| v.sites().clone());
+            state.add_site_layer("undo", sites)
+        } else {
+            let sites = state.voronoi.as_ref().map(|v| v.sites().clone());
+            state.add_site_layer("snapshot", sites)
+        };
+    } else if input.just_pressed(KeyCode::Delete) {
+        respawn |= state.remove_site_layer();
+    }
+
+    for (i, key) in LAYER_KEYS.iter().enumerate() {
+        if input.just_pressed(*key) {
+            if input.pressed(KeyCode::LControl) {
+                respawn |= state.activate_site_layer(i);
+            } else if let Some(layer) = state.site_layers.get_mut(i) {
+                layer.visible = !layer.visible;
+                respawn = true;
+            }
+        }
+    }
+
     // span new voronoi with new points
     if input.just_pressed(KeyCode::G) {
         respawn = true;
@@ -612,16 +1248,65 @@ fn handle_input(
     }
 
     if respawn {
+        state.generation += 1;
         for e in query.iter() {
             commands.entity(e).despawn();
         }
 
+        if state.grid.enabled {
+            if let Some(mesh) = state.grid.build_mesh(&state.bounding_box, Color::rgb(0.25, 0.25, 0.25)) {
+                commands
+                    .spawn_bundle(ColorBundle {
+                        mesh: meshes.add(mesh),
+                        ..Default::default()
+                    })
+                    .insert(Object);
+            }
+        }
+
+        // hiding the active layer hides the diagram, and the split pane built from it
+        let active_visible = state.active_site_layer_visible();
+
         // may not exist after clean up
-        if let Some(voronoi) = &state.voronoi {
+        if let Some(voronoi) = state.voronoi.as_ref().filter(|_| active_visible) {
             // FIXME: bring this back conditionally when path is set
             //spawn_voronoi_cell(&mut commands, &mut meshes, &voronoi.cell(0));
 
-            spawn_voronoi(commands, meshes, voronoi, &state.voronoi_opts);
+            spawn_voronoi(&mut commands, &mut meshes, voronoi, &state.render_layers, Vec3::ZERO);
+        }
+
+        if let Some(sites) = state.voronoi.as_ref().map(|v| v.sites().clone()) {
+            let (builder, bounding_box) = (state.new_builder(), state.bounding_box.clone());
+            if let Some(split) = state.split.as_mut() {
+                split.rebuild(builder, sites, &bounding_box);
+            }
+        }
+
+        if let Some(split) = state.split.as_ref().filter(|_| active_visible) {
+            if let Some(voronoi) = &split.voronoi {
+                spawn_voronoi(&mut commands, &mut meshes, voronoi, &state.render_layers, split.offset);
+            }
+        }
+
+        let bounding_box_layer = state.render_layers.get(LayerKind::BoundingBox);
+        if bounding_box_layer.visible {
+            let corners = utils::bounding_box_corners(&state.bounding_box);
+            commands
+                .spawn_bundle(ColorBundle {
+                    mesh: meshes.add(utils::build_line_strip(&corners, true, bounding_box_layer.color())),
+                    transform: Transform::from_translation(Vec3::Y * state.render_layers.height(LayerKind::BoundingBox)),
+                    ..bounding_box_layer.bundle()
+                })
+                .insert(Object);
+        }
+
+        let overlays_layer = state.render_layers.get(LayerKind::Overlays);
+        if overlays_layer.visible {
+            let height = state.render_layers.height(LayerKind::Overlays);
+            // the active layer is the diagram itself
+            for (i, layer) in state.site_layers.iter().enumerate().filter(|(i, l)| l.visible && *i != state.active_layer) {
+                layer.spawn(&mut commands, &mut meshes, height, i, overlays_layer.opacity);
+            }
         }
     }
 
@@ -631,16 +1316,131 @@ fn handle_input(
 
     let updates: [String; STRING_UI_COUNT] = [
         format!("[C] Clip mode: {:?}", state.clip_behavior),
-        format!("[P] Voronoi mesh render mode: {:?}", state.voronoi_opts.voronoi_topoloy),
-        format!("[O] Delauney mesh render mode: {:?}", state.voronoi_opts.delauney_topoloy),
         format!("[PgUp/PgDown] Bounding box: {:.2}", state.bounding_box.width()),
-        format!("[Home] Site type: {:?}", state.site_type),
-        format!("[ArrowUp/ArrowDown/G/MouseClick] # of Sites: {}", state.voronoi.as_ref().map_or(0, |v| v.sites().len())),
-        "[W/S/R] Camera Movement".to_string(),
+        format!("[Home/End/Slash/;/'/I] Site type: {}", state.generators.describe()),
+        format!("[G/Shift+Return] Seed: {}", state.seed),
+        format!("[Ctrl+ArrowUp/ArrowDown/G/MouseClick] # of Sites: {}", state.voronoi.as_ref().map_or(0, |v| v.sites().len())),
+        format!("[W/S/R/Arrows/Ctrl+MouseMiddle+Drag/Wheel/F/Shift+F/T/Tab] Camera: {}", camera_query.iter().next().map_or(String::new(), |view| {
+            format!("{}{}", if view.orthographic { "orthographic" } else { "perspective" }, if view.orbit { ", orbit" } else { "" })
+        })),
+        format!("[Ctrl+1-9/1-9] Camera bookmarks: {}", state.camera_bookmarks.iter().enumerate()
+            .filter(|(_, b)| b.is_some())
+            .map(|(i, _)| (i + 1).to_string())
+            .collect::<Vec<String>>()
+            .join(" ")),
         "[L] Lloyd relaxation".to_string(),
+        format!("[H/Shift+C/Shift+L] Split view: {}", state.split.as_ref().map_or("off".to_string(), |split| split.describe())),
+        format!("[Q+Drag/[/]/-/=] Brush radius: {:.2}, density: {:.0}", state.brush.radius, state.brush.density),
+        format!("[J/,/.] Grid snap: {}, spacing: {}", if state.grid.enabled { "on" } else { "off" }, state.grid.spacing),
+        format!("[M/N] Symmetry: {:?}", state.symmetry),
+        format!("[K] Measure: {}", measure::describe(&state)),
+        format!("[U/Shift+U/Ctrl+U] Curve: {}", state.curve.as_ref().map_or("off".to_string(), |curve| {
+            format!("{} (click to add, right click to undo, U to place sites, Esc to cancel)", curve.describe())
+        })),
+        format!("[X/Shift+X/Ctrl+X/O] Region: {}", match (state.region_drawing.as_ref(), state.region.as_ref()) {
+            (Some(vertices), _) => format!("drawing, {} vertices (click to add, right click to undo, X to finish, Esc to cancel)", vertices.len()),
+            (None, Some(region)) => format!("{}, boundary {}", region.describe(), if state.region_boundary { "on" } else { "off" }),
+            (None, None) => "whole bounding box".to_string(),
+        }),
+        "[LAlt] Preview site insertion".to_string(),
+        format!("[D] Diff with previous state: {}", if state.diff { "on (green added, red removed, yellow reshaped)" } else { "off" }),
+        format!("[Ins/Shift+Ins/Del/Num1-9/Ctrl+Num1-9] Layers: {}", state.site_layers.iter().enumerate()
+            .map(|(i, l)| l.describe(i, state.site_layer_voronoi(i), i == state.active_layer))
+            .collect::<Vec<String>>()
+            .join(" ")),
+        if let Some(prompt) = state.prompt.as_ref() {
+            format!("[Return/Esc] {}: {}_", prompt.label(), prompt.text)
+        } else if let Some(p) = state.selected_site.and_then(|s| state.voronoi.as_ref().and_then(|v| v.sites().get(s))) {
+            format!("[Return/E] Type site, edit selected ({:.4}, {:.4})", p.x, p.y)
+        } else {
+            "[Return/MouseMiddle] Type site, select site".to_string()
+        },
     ];
 
     for (mut text, update) in query_text.iter_mut().zip(&updates) {
         text.sections[0].value = update.clone();
     }
-}
\ No newline at end of file
+
+    // top of the draw order first
+    let layer_updates = std::iter::once("[F1-F8/Shift: raise/Ctrl: color/Shift+Ctrl: opacity] Layers".to_string())
+        .chain(state.render_layers.iter().rev().map(|l| {
+            let key = RENDER_LAYER_KEYS.iter().position(|(kind, _)| *kind == l.kind).unwrap() + 1;
+            format!("F{} {}", key, l.describe())
+        }));
+    for (mut text, update) in query_layer_text.iter_mut().zip(layer_updates) {
+        text.sections[0].value = update;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use site_generators::StressCase;
+
+    #[test]
+    fn build_voronoi_survives_stress_cases() {
+        let bounding_box = BoundingBox::new_centered_square(2.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let build = |case, rng: &mut StdRng| build_voronoi(VoronoiBuilder::default()
+            .set_bounding_box(bounding_box.clone())
+            .set_sites(site_generators::stress_sites(&bounding_box, 100, case, rng)));
+
+        // collinear sites have no triangulation, which is reported as a failed build
+        assert!(build(StressCase::Collinear, &mut rng).is_none());
+
+        // the other cases may or may not build, but must not panic
+        for case in StressCase::ALL.iter().filter(|c| **c != StressCase::Collinear) {
+            if let Some(v) = build(*case, &mut rng) {
+                assert!(!v.sites().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn replace_with_failed_build_keeps_diagram() {
+        let mut state = State { bounding_box: BoundingBox::new_centered_square(2.0), ..Default::default() };
+        state.new_voronoi(20);
+        let sites = state.voronoi.as_ref().map(|v| v.sites().len());
+        assert!(sites.is_some());
+
+        state.replace(None);
+        assert_eq!(sites, state.voronoi.as_ref().map(|v| v.sites().len()));
+        assert!(state.undo_list.is_empty());
+
+        // edits after a failed build still work
+        state.add_site_to_voronoi(Point { x: 0.5, y: 0.5 });
+        assert_eq!(sites.map(|s| s + 1), state.voronoi.as_ref().map(|v| v.sites().len()));
+    }
+
+    #[test]
+    fn failed_first_build_falls_back_to_random_sites() {
+        let mut state = State { bounding_box: BoundingBox::new_centered_square(2.0), ..Default::default() };
+        // the first stress case is collinear, which does not build
+        state.generators.select(STRESS_GENERATOR);
+        state.new_voronoi(20);
+
+        assert!(state.voronoi.is_some());
+        assert_eq!(RANDOM_GENERATOR, state.generators.selected().name());
+    }
+
+    #[test]
+    fn activate_site_layer_swaps_diagram_and_history() {
+        let mut state = State { bounding_box: BoundingBox::new_centered_square(2.0), ..Default::default() };
+        state.new_voronoi(20);
+        state.add_site_to_voronoi(Point { x: 0.5, y: 0.5 });
+        let sites = state.voronoi.as_ref().map(|v| v.sites().len());
+        assert!(state.add_site_layer("snapshot", Some(vec![Point { x: 0.1, y: 0.1 }, Point { x: -0.1, y: -0.1 }, Point { x: 0.1, y: -0.1 }])));
+        assert_eq!(2, state.site_layers.len());
+
+        assert!(state.activate_site_layer(1));
+        assert_eq!(Some(3), state.voronoi.as_ref().map(|v| v.sites().len()));
+        assert!(state.undo_list.is_empty());
+        assert!(!state.remove_site_layer());
+
+        // the first layer kept its diagram and undo history
+        assert!(state.activate_site_layer(0));
+        assert_eq!(sites, state.voronoi.as_ref().map(|v| v.sites().len()));
+        assert_eq!(1, state.undo_list.len());
+        assert!(state.remove_site_layer());
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::{
+    prelude::*,
+    render::camera::Camera,
+};
+use voronoice::Point;
+
+use super::{
+    utils::{self, distance},
+    ColorBundle,
+    State,
+    camera::MainCamera,
+};
+
+/// Points clicked in measurement mode. Two points measure a distance, three points measure an angle at the middle point.
+#[derive(Debug, Default)]
+pub struct Measurement {
+    pub points: Vec<Point>,
+}
+
+impl Measurement {
+    /// Adds a point, starting a new measurement if there are already three.
+    pub fn push(&mut self, point: Point) {
+        if self.points.len() == 3 {
+            self.points.clear();
+        }
+        self.points.push(point);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Distances between consecutive points.
+    pub fn distances(&self) -> Vec<f64> {
+        self.points.windows(2).map(|w| distance(&w[0], &w[1])).collect()
+    }
+
+    /// Angle, in degrees, at the second point.
+    pub fn angle(&self) -> Option<f64> {
+        if let [a, b, c] = &self.points[..] {
+            Some(angle(a, b, c))
+        } else {
+            None
+        }
+    }
+}
+
+/// Angle in degrees between segments `vertex -> a` and `vertex -> b`.
+pub fn angle(a: &Point, vertex: &Point, b: &Point) -> f64 {
+    let (ax, ay) = (a.x - vertex.x, a.y - vertex.y);
+    let (bx, by) = (b.x - vertex.x, b.y - vertex.y);
+    (ax * by - ay * bx).atan2(ax * bx + ay * by).abs().to_degrees()
+}
+
+/// Area of a simple polygon (shoelace formula).
+pub fn polygon_area(vertices: &[Point]) -> f64 {
+    let n = vertices.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (&vertices[i], &vertices[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f64>()
+        .abs() / 2.0
+}
+
+/// Perimeter of a closed polygon.
+pub fn polygon_perimeter(vertices: &[Point]) -> f64 {
+    let n = vertices.len();
+    (0..n).map(|i| distance(&vertices[i], &vertices[(i + 1) % n])).sum()
+}
+
+/// Text positioned on screen over a point in the world.
+pub struct WorldLabel {
+    pub world_pos: Vec3,
+}
+
+pub struct MeasurementOverlay;
+
+const OVERLAY_COLOR: Color = Color::YELLOW;
+const CELL_OVERLAY_COLOR: Color = Color::CYAN;
+
+fn spawn_label(commands: &mut Commands, font: Handle<Font>, text: String, point: &Point, color: Color) {
+    commands.spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            text: Text::with_section(
+                text,
+                TextStyle {
+                    font,
+                    font_size: 20.0,
+                    color,
+                },
+                TextAlignment::default()),
+            ..Default::default()
+        })
+        .insert(WorldLabel { world_pos: Vec3::from(utils::point_to_f32_vec(point)) })
+        .insert(MeasurementOverlay);
+}
+
+/// Summary of the measurement for the HUD.
+pub fn describe(state: &State) -> String {
+    let measurement = if let Some(m) = state.measurement.as_ref() {
+        m
+    } else {
+        return "off".to_string();
+    };
+
+    let mut parts: Vec<String> = measurement.distances().iter().map(|d| format!("d = {:.4}", d)).collect();
+    if let Some(a) = measurement.angle() {
+        parts.push(format!("angle = {:.2}°", a));
+    }
+    if let Some((area, perimeter)) = selected_cell_metrics(state) {
+        parts.push(format!("cell area = {:.4}, perimeter = {:.4}", area, perimeter));
+    }
+
+    if parts.is_empty() {
+        "click points, middle click a cell".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+fn selected_cell_vertices(state: &State) -> Option<Vec<Point>> {
+    let voronoi = state.voronoi.as_ref()?;
+    let site = state.selected_site.filter(|s| *s < voronoi.sites().len())?;
+    Some(voronoi.cell(site).iter_vertices().cloned().collect())
+}
+
+fn selected_cell_metrics(state: &State) -> Option<(f64, f64)> {
+    selected_cell_vertices(state).map(|v| (polygon_area(&v), polygon_perimeter(&v)))
+}
+
+/// Redraws the measurement lines, the picked cell outline and their labels when the measurement or the diagram changes.
+pub fn update_measurement_overlay(
+    state: Res<State>,
+    mut rendered: Local<Option<(u64, Vec<(f64, f64)>, Option<usize>)>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, With<MeasurementOverlay>>) {
+
+    let key = state.measurement.as_ref()
+        .map(|m| (state.generation, m.points.iter().map(|p| (p.x, p.y)).collect(), state.selected_site));
+    if *rendered == key {
+        return;
+    }
+    *rendered = key;
+
+    for e in query.iter() {
+        commands.entity(e).despawn();
+    }
+
+    let measurement = if let Some(m) = state.measurement.as_ref() {
+        m
+    } else {
+        return;
+    };
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let transform = Transform::from_translation(Vec3::Y * state.render_layers.tool_height());
+
+    if measurement.points.len() > 1 {
+        commands
+            .spawn_bundle(ColorBundle {
+                mesh: meshes.add(utils::build_line_strip(&measurement.points, false, OVERLAY_COLOR)),
+                transform,
+                ..Default::default()
+            })
+            .insert(MeasurementOverlay);
+
+        for (w, d) in measurement.points.windows(2).zip(measurement.distances()) {
+            let middle = Point { x: (w[0].x + w[1].x) / 2.0, y: (w[0].y + w[1].y) / 2.0 };
+            spawn_label(&mut commands, font.clone(), format!("{:.4}", d), &middle, OVERLAY_COLOR);
+        }
+    }
+
+    if let Some(a) = measurement.angle() {
+        spawn_label(&mut commands, font.clone(), format!("{:.2}°", a), &measurement.points[1], OVERLAY_COLOR);
+    }
+
+    if let Some(vertices) = selected_cell_vertices(&state) {
+        commands
+            .spawn_bundle(ColorBundle {
+                mesh: meshes.add(utils::build_line_strip(&vertices, true, CELL_OVERLAY_COLOR)),
+                transform,
+                ..Default::default()
+            })
+            .insert(MeasurementOverlay);
+
+        let site = state.voronoi.as_ref().unwrap().sites()[state.selected_site.unwrap()].clone();
+        let text = format!("A {:.4}\nP {:.4}", polygon_area(&vertices), polygon_perimeter(&vertices));
+        spawn_label(&mut commands, font, text, &site, CELL_OVERLAY_COLOR);
+    }
+}
+
+/// Keeps [`WorldLabel`] texts over their world position.
+pub fn position_world_labels(
+    mut query: Query<(&WorldLabel, &mut Style, &mut Visible)>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    windows: Res<Windows>) {
+
+    if let (Some((camera, camera_transform)), Some(window)) = (camera_query.iter().next(), windows.get_primary()) {
+        for (label, mut style, mut visible) in query.iter_mut() {
+            if let Some(screen_pos) = camera.world_to_screen(&windows, camera_transform, label.world_pos) {
+                style.position.left = Val::Px(screen_pos.x);
+                style.position.top = Val::Px(window.height() - screen_pos.y);
+                visible.is_visible = true;
+            } else {
+                visible.is_visible = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygon_area_and_perimeter_of_square() {
+        let square = [
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 2.0, y: 0.0 },
+            Point { x: 2.0, y: 2.0 },
+            Point { x: 0.0, y: 2.0 },
+        ];
+        assert_eq!(4.0, polygon_area(&square));
+        assert_eq!(8.0, polygon_perimeter(&square));
+    }
+
+    #[test]
+    fn measurement_expect_angle_on_third_point() {
+        let mut m = Measurement::default();
+        m.push(Point { x: 1.0, y: 0.0 });
+        m.push(Point { x: 0.0, y: 0.0 });
+        assert_eq!(vec![1.0], m.distances());
+        assert_eq!(None, m.angle());
+
+        m.push(Point { x: 0.0, y: 1.0 });
+        assert!((m.angle().unwrap() - 90.0).abs() < 1e-12);
+
+        // fourth point starts over
+        m.push(Point { x: 5.0, y: 5.0 });
+        assert_eq!(1, m.points.len());
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::{
+    prelude::*,
+    render::{camera::Camera, texture::{Extent3d, Texture, TextureDimension, TextureFormat}},
+};
+
+use super::{
+    utils,
+    State,
+    camera::{self, CameraView, MainCamera},
+};
+
+/// Width and height of the minimap, in pixels.
+const MINIMAP_SIZE: u32 = 192;
+/// Distance from the minimap to the bottom right corner of the window, in pixels.
+const MINIMAP_MARGIN: f32 = 10.0;
+/// Sites are counted in square bins of this many pixels.
+const BIN_SIZE: u32 = 4;
+const BINS: u32 = MINIMAP_SIZE / BIN_SIZE;
+
+const BACKGROUND_COLOR: [u8; 4] = [20, 20, 20, 200];
+const BOUNDING_BOX_COLOR: [u8; 4] = [255, 255, 255, 255];
+const VIEW_COLOR: [u8; 4] = [255, 220, 0, 255];
+
+/// Overview of the whole diagram shown in the bottom right corner.
+pub struct Minimap {
+    texture: Handle<Texture>,
+    /// Plane coordinates (see [`plane_coords`]) at the center of the minimap
+    center: Vec2,
+    /// World units covered by one minimap pixel
+    units_per_pixel: f32,
+}
+
+#[derive(Default)]
+pub struct MinimapCache {
+    generation: Option<u64>,
+    /// Number of sites in each bin, row by row from the top
+    bins: Vec<u32>,
+    max_bin: u32,
+    bounding_box: Vec<Vec2>,
+    view: Vec<(i32, i32)>,
+}
+
+/// Coordinates along the screen right and up axes of the top down camera, so the minimap has the same orientation as the default view.
+fn plane_coords(world_pos: Vec3) -> Vec2 {
+    let rotation = camera::top_down_rotation();
+    Vec2::new(world_pos.dot(rotation * Vec3::X), world_pos.dot(rotation * Vec3::Y))
+}
+
+fn plane_to_world(coords: Vec2) -> Vec3 {
+    let rotation = camera::top_down_rotation();
+    rotation * Vec3::X * coords.x + rotation * Vec3::Y * coords.y
+}
+
+/// Minimap corners on screen (origin at the bottom left of the window).
+pub fn screen_rect(window: &Window) -> (Vec2, Vec2) {
+    let max = Vec2::new(window.width() - MINIMAP_MARGIN, MINIMAP_MARGIN + MINIMAP_SIZE as f32);
+    (max - Vec2::splat(MINIMAP_SIZE as f32), max)
+}
+
+pub fn contains(window: &Window, screen_pos: Vec2) -> bool {
+    let (min, max) = screen_rect(window);
+    screen_pos.x >= min.x && screen_pos.x <= max.x && screen_pos.y >= min.y && screen_pos.y <= max.y
+}
+
+pub fn setup_minimap(
+    mut commands: Commands,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>) {
+
+    let texture = textures.add(Texture::new(
+        Extent3d::new(MINIMAP_SIZE, MINIMAP_SIZE, 1),
+        TextureDimension::D2,
+        BACKGROUND_COLOR.repeat((MINIMAP_SIZE * MINIMAP_SIZE) as usize),
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    commands.spawn_bundle(ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(MINIMAP_MARGIN),
+                    bottom: Val::Px(MINIMAP_MARGIN),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(MINIMAP_SIZE as f32), Val::Px(MINIMAP_SIZE as f32)),
+                ..Default::default()
+            },
+            material: materials.add(ColorMaterial::texture(texture.clone())),
+            ..Default::default()
+        })
+        .insert(Minimap {
+            texture,
+            center: Vec2::ZERO,
+            units_per_pixel: 1.0,
+        });
+}
+
+impl Minimap {
+    fn to_pixel(&self, coords: Vec2) -> Vec2 {
+        let half = MINIMAP_SIZE as f32 / 2.0;
+        let p = (coords - self.center) / self.units_per_pixel;
+        // texture rows go from top to bottom
+        Vec2::new(half + p.x, half - p.y)
+    }
+
+    fn from_pixel(&self, pixel: Vec2) -> Vec2 {
+        let half = MINIMAP_SIZE as f32 / 2.0;
+        self.center + Vec2::new(pixel.x - half, half - pixel.y) * self.units_per_pixel
+    }
+
+    /// Frames the bounding box and all sites, which may lie outside of it.
+    fn frame(&mut self, points: &[Vec2]) {
+        let (mut min, mut max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+        for p in points {
+            min = min.min(*p);
+            max = max.max(*p);
+        }
+
+        let size = (max - min).max_element().max(f32::EPSILON);
+        self.center = (min + max) / 2.0;
+        self.units_per_pixel = size * 1.1 / MINIMAP_SIZE as f32;
+    }
+}
+
+fn set_pixel(data: &mut [u8], x: i32, y: i32, color: [u8; 4]) {
+    if x >= 0 && y >= 0 && (x as u32) < MINIMAP_SIZE && (y as u32) < MINIMAP_SIZE {
+        let i = 4 * (y as usize * MINIMAP_SIZE as usize + x as usize);
+        data[i..i + 4].copy_from_slice(&color);
+    }
+}
+
+fn draw_line(data: &mut [u8], a: (i32, i32), b: (i32, i32), color: [u8; 4]) {
+    let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).max(1);
+    for s in 0..=steps {
+        let t = s as f32 / steps as f32;
+        let x = a.0 as f32 + t * (b.0 - a.0) as f32;
+        let y = a.1 as f32 + t * (b.1 - a.1) as f32;
+        set_pixel(data, x.round() as i32, y.round() as i32, color);
+    }
+}
+
+fn draw_polygon(data: &mut [u8], points: &[(i32, i32)], color: [u8; 4]) {
+    for i in 0..points.len() {
+        draw_line(data, points[i], points[(i + 1) % points.len()], color);
+    }
+}
+
+/// Clamps a pixel just outside the minimap, so lines of a view much larger than the minimap stay short.
+/// Axis aligned edges are not distorted by it.
+fn clamp_pixel(p: Vec2) -> (i32, i32) {
+    let limit = MINIMAP_SIZE as f32;
+    (p.x.max(-1.0).min(limit).round() as i32, p.y.max(-1.0).min(limit).round() as i32)
+}
+
+/// Redraws the minimap when the diagram or the view changes. Clicking or dragging on the minimap recenters the main camera.
+pub fn update_minimap(
+    state: Res<State>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut minimap_query: Query<&mut Minimap>,
+    mut camera_query: Query<(&Camera, &Transform, &mut CameraView), With<MainCamera>>,
+    mut cache: Local<MinimapCache>) {
+
+    let (mut minimap, window) = match (minimap_query.iter_mut().next(), windows.get_primary()) {
+        (Some(m), Some(w)) => (m, w),
+        _ => return,
+    };
+    let (camera, camera_transform, mut view) = if let Some(c) = camera_query.iter_mut().next() {
+        c
+    } else {
+        return;
+    };
+
+    if mouse_button_input.pressed(MouseButton::Left) {
+        if let Some(cursor) = window.cursor_position().filter(|c| contains(window, *c)) {
+            let (min, max) = screen_rect(window);
+            let pixel = Vec2::new(cursor.x - min.x, max.y - cursor.y);
+            view.focus = plane_to_world(minimap.from_pixel(pixel));
+        }
+    }
+
+    let diagram_changed = cache.generation != Some(state.generation);
+    if diagram_changed {
+        cache.generation = Some(state.generation);
+
+        let sites: Vec<Vec2> = state.voronoi.as_ref()
+            .map_or(vec![], |v| v.sites().iter().map(|s| plane_coords(Vec3::from(utils::point_to_f32_vec(s)))).collect());
+
+        cache.bounding_box = utils::bounding_box_corners(&state.bounding_box).iter()
+            .map(|p| plane_coords(Vec3::from(utils::point_to_f32_vec(p))))
+            .collect();
+
+        minimap.frame(&sites.iter().chain(cache.bounding_box.iter()).copied().collect::<Vec<Vec2>>());
+
+        let mut bins = vec![0; (BINS * BINS) as usize];
+        for s in sites.iter() {
+            let p = minimap.to_pixel(*s) / BIN_SIZE as f32;
+            if p.x >= 0.0 && p.y >= 0.0 && (p.x as u32) < BINS && (p.y as u32) < BINS {
+                bins[(p.y as u32 * BINS + p.x as u32) as usize] += 1;
+            }
+        }
+        cache.max_bin = bins.iter().copied().max().unwrap_or(0);
+        cache.bins = bins;
+    }
+
+    // view rectangle is where the window corners hit the diagram plane
+    let (width, height) = (window.width(), window.height());
+    let view_polygon: Vec<(i32, i32)> = [Vec2::new(0.0, 0.0), Vec2::new(width, 0.0), Vec2::new(width, height), Vec2::new(0.0, height)].iter()
+        .filter_map(|corner| camera::screen_to_plane(*corner, window, camera, camera_transform))
+        .map(|p| clamp_pixel(minimap.to_pixel(plane_coords(p))))
+        .collect();
+
+    if !diagram_changed && view_polygon == cache.view {
+        return;
+    }
+
+    let mut data = BACKGROUND_COLOR.repeat((MINIMAP_SIZE * MINIMAP_SIZE) as usize);
+
+    // site density, brighter where there are more sites
+    for by in 0..BINS {
+        for bx in 0..BINS {
+            let count = cache.bins[(by * BINS + bx) as usize];
+            if count > 0 {
+                let intensity = 60 + (195.0 * (count as f32 / cache.max_bin as f32).sqrt()) as u8;
+                let color = [intensity / 2, intensity / 2, intensity, 255];
+                for y in 0..BIN_SIZE {
+                    for x in 0..BIN_SIZE {
+                        set_pixel(&mut data, (bx * BIN_SIZE + x) as i32, (by * BIN_SIZE + y) as i32, color);
+                    }
+                }
+            }
+        }
+    }
+
+    let bounding_box: Vec<(i32, i32)> = cache.bounding_box.iter().map(|p| clamp_pixel(minimap.to_pixel(*p))).collect();
+    draw_polygon(&mut data, &bounding_box, BOUNDING_BOX_COLOR);
+    draw_polygon(&mut data, &view_polygon, VIEW_COLOR);
+    cache.view = view_polygon;
+
+    if let Some(texture) = textures.get_mut(&minimap.texture) {
+        texture.data = data;
+    }
+}
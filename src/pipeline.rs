@@ -18,6 +18,9 @@ use bevy::{
 pub const FORWARD_PIPELINE_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 12148362314032771289);
 
+pub const FORWARD_BLENDED_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 4630812274620364021);
+
 pub(crate) fn build_forward_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
     PipelineDescriptor {
         ..PipelineDescriptor::default_config(ShaderStages {
@@ -33,6 +36,18 @@ pub(crate) fn build_forward_pipeline(shaders: &mut Assets<Shader>) -> PipelineDe
     }
 }
 
+/// Same as [`build_forward_pipeline`] (which already alpha blends), but with depth tested and not written,
+/// so translucent meshes do not hide each other or what is drawn after them.
+pub(crate) fn build_forward_blended_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    let mut pipeline = build_forward_pipeline(shaders);
+
+    if let Some(depth_stencil) = pipeline.depth_stencil.as_mut() {
+        depth_stencil.depth_write_enabled = false;
+    }
+
+    pipeline
+}
+
 #[derive(Default)]
 pub struct VertexColorPlugin;
 
@@ -57,12 +72,17 @@ impl Plugin for VertexColorPlugin {
 
         let mut shaders = app.world_mut().get_resource_mut::<Assets<Shader>>().unwrap();
         let pipeline = build_forward_pipeline(&mut shaders);
+        let blended_pipeline = build_forward_blended_pipeline(&mut shaders);
 
         let mut pipelines = app.world_mut().get_resource_mut::<Assets<PipelineDescriptor>>().unwrap();
         pipelines.set_untracked(
             FORWARD_PIPELINE_HANDLE,
             pipeline,
         );
+        pipelines.set_untracked(
+            FORWARD_BLENDED_PIPELINE_HANDLE,
+            blended_pipeline,
+        );
 
         app.world_mut().get_resource_mut::<Assets<VertexColor>>()
             .unwrap()
@@ -101,4 +121,20 @@ impl Default for ColorBundle {
             color: Default::default(),
         }
     }
-}
\ No newline at end of file
+}
+
+impl ColorBundle {
+    /// Bundle for meshes with translucent vertex colors, sorted as transparent so they are drawn after the opaque ones.
+    pub fn translucent() -> Self {
+        Self {
+            render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                FORWARD_BLENDED_PIPELINE_HANDLE.typed(),
+            )]),
+            visible: Visible {
+                is_visible: true,
+                is_transparent: true,
+            },
+            ..Default::default()
+        }
+    }
+}
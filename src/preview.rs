@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use voronoice::{Point, Voronoi, VoronoiCell};
+
+use super::{
+    build_voronoi,
+    ColorBundle,
+    Mouse,
+    State,
+    voronoi_cell_mesh_generator::VoronoiCellMeshGenerator,
+};
+
+/// Time the cursor must rest before the preview diagram is rebuilt.
+const PREVIEW_DEBOUNCE_SECONDS: f64 = 0.1;
+
+/// Vertices closer than this are considered unchanged.
+const VERTEX_EPSILON: f64 = 1e-9;
+
+/// Ghost outline of a cell that would change if a site was inserted at the cursor.
+pub struct InsertPreview;
+
+#[derive(Default)]
+pub struct PreviewState {
+    /// Insertion point the ghost is showing, and the diagram generation it was computed for
+    rendered: Option<(f64, f64, u64)>,
+    /// Insertion point waiting for the cursor to rest
+    pending: Option<(Point, f64)>,
+}
+
+fn color_ghost(_i: usize) -> Color {
+    Color::rgb(0.3, 0.6, 1.0)
+}
+
+fn color_ghost_new(_i: usize) -> Color {
+    Color::rgb(0.6, 0.9, 1.0)
+}
+
+fn color_ghost_new_fill(_i: usize) -> Color {
+    Color::rgba(0.6, 0.9, 1.0, 0.25)
+}
+
+/// Key of a site position, to match cells of two diagrams by their site rather than by index.
+pub fn site_key(p: &Point) -> (u64, u64) {
+    (p.x.to_bits(), p.y.to_bits())
+}
+
+/// Returns true if the two cells do not have the same vertices.
+pub fn cell_changed(a: &VoronoiCell, b: &VoronoiCell) -> bool {
+    let a: Vec<Point> = a.iter_vertices().cloned().collect();
+    let b: Vec<Point> = b.iter_vertices().cloned().collect();
+    !same_polygon(&a, &b)
+}
+
+#[inline]
+fn same_vertex(a: &Point, b: &Point) -> bool {
+    (a.x - b.x).abs() <= VERTEX_EPSILON && (a.y - b.y).abs() <= VERTEX_EPSILON
+}
+
+/// Returns true if both polygons have the same vertices in the same order, regardless of which vertex comes first.
+fn same_polygon(a: &[Point], b: &[Point]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    match a.first() {
+        None => true,
+        Some(first) => (0..b.len())
+            .filter(|start| same_vertex(first, &b[*start]))
+            .any(|start| a.iter().enumerate().all(|(i, v)| same_vertex(v, &b[(start + i) % b.len()]))),
+    }
+}
+
+fn spawn_ghost_cell(commands: &mut Commands, meshes: &mut ResMut<Assets<Mesh>>, height: f32, cell: &VoronoiCell, coloring: fn(usize) -> Color) {
+    let mesh_generator = VoronoiCellMeshGenerator {
+        cell,
+        coloring
+    };
+
+    commands
+        .spawn_bundle(
+            ColorBundle {
+                mesh: meshes.add(mesh_generator.build_outline_mesh()),
+                transform: Transform::from_translation(Vec3::Y * height),
+                ..Default::default()
+        })
+        .insert(InsertPreview);
+}
+
+fn spawn_ghost_fill(commands: &mut Commands, meshes: &mut ResMut<Assets<Mesh>>, height: f32, cell: &VoronoiCell, coloring: fn(usize) -> Color) {
+    let mesh_generator = VoronoiCellMeshGenerator {
+        cell,
+        coloring
+    };
+
+    commands
+        .spawn_bundle(
+            ColorBundle {
+                mesh: meshes.add(mesh_generator.build_voronoi_mesh()),
+                transform: Transform::from_translation(Vec3::Y * height),
+                ..ColorBundle::translucent()
+        })
+        .insert(InsertPreview);
+}
+
+/// While left alt is held, shows the cells that would change if a site was added under the cursor (with its symmetric images).
+pub fn update_insert_preview(
+    state: Res<State>,
+    input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mouse_query: Query<&Mouse>,
+    mut preview: Local<PreviewState>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<Entity, With<InsertPreview>>) {
+
+    let mouse = mouse_query.iter().next().unwrap();
+    let voronoi = match state.voronoi.as_ref() {
+        Some(v) if input.pressed(KeyCode::LAlt) && mouse.on_plane && state.prompt.is_none() => v,
+        _ => {
+            if preview.rendered.take().is_some() {
+                for e in query.iter() {
+                    commands.entity(e).despawn();
+                }
+            }
+            preview.pending = None;
+            return;
+        }
+    };
+
+    let now = time.seconds_since_startup();
+    let point = state.placement_point(mouse.world_pos);
+
+    // restart the wait every time the cursor moves
+    let moved = match preview.pending.as_ref() {
+        Some((p, _)) => p.x != point.x || p.y != point.y,
+        None => preview.rendered.map_or(true, |(x, y, g)| x != point.x || y != point.y || g != state.generation),
+    };
+    if moved {
+        preview.pending = Some((point, now));
+        return;
+    }
+
+    let point = match preview.pending.take() {
+        Some((p, since)) if now - since >= PREVIEW_DEBOUNCE_SECONDS => p,
+        pending => {
+            preview.pending = pending;
+            return;
+        }
+    };
+
+    for e in query.iter() {
+        commands.entity(e).despawn();
+    }
+    preview.rendered = Some((point.x, point.y, state.generation));
+
+    // the same sites a click would add, with their symmetric images
+    let new_sites = state.insertion_sites(&point);
+    if new_sites.is_empty() {
+        return;
+    }
+    let mut sites = voronoi.sites().clone();
+    sites.extend(new_sites);
+    let preview_voronoi: Voronoi = if let Some(v) = build_voronoi(state.new_builder().set_sites(sites)) {
+        v
+    } else {
+        return;
+    };
+
+    // above the diagram layers so ghosts lying on cell edges are not hidden by them
+    let height = state.render_layers.tool_height();
+    // cells are matched by site position, as clipping can drop sites and shift the indices of the others
+    let existing: HashMap<(u64, u64), usize> = voronoi.sites().iter().enumerate()
+        .map(|(i, p)| (site_key(p), i))
+        .collect();
+    for cell in preview_voronoi.iter_cells() {
+        match existing.get(&site_key(cell.site_position())) {
+            None => {
+                spawn_ghost_cell(&mut commands, &mut meshes, height, &cell, color_ghost_new);
+                spawn_ghost_fill(&mut commands, &mut meshes, height, &cell, color_ghost_new_fill);
+            },
+            Some(site) => if cell_changed(&cell, &voronoi.cell(*site)) {
+                spawn_ghost_cell(&mut commands, &mut meshes, height, &cell, color_ghost);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_polygon_expect_rotation_ignored() {
+        let a = [Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 1.0, y: 1.0 }];
+        let rotated = [Point { x: 1.0, y: 1.0 }, Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }];
+        let reversed = [Point { x: 1.0, y: 1.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 0.0, y: 0.0 }];
+
+        assert!(same_polygon(&a, &rotated));
+        assert!(!same_polygon(&a, &reversed));
+        assert!(!same_polygon(&a, &a[..2]));
+    }
+}
@@ -0,0 +1,106 @@
+use voronoice::Point;
+
+/// What the text typed in a [`TextPrompt`] is used for.
+#[derive(Debug, Clone, Copy)]
+pub enum PromptKind {
+    /// Adds a site at the typed coordinates.
+    AddSite,
+    /// Moves the site with the given index, found by its position `(x, y)` when the prompt is submitted, to the typed coordinates.
+    EditSite { site: usize, x: f64, y: f64 },
+    /// Regenerates the diagram from the typed random seed.
+    Seed,
+    /// Loads the density image at the typed path, relative to the assets folder.
+    ImagePath,
+    /// Loads the region polygon from the file at the typed path.
+    RegionPath,
+    /// Places sites along the curve being drawn, with the typed count and jitter.
+    CurveSites,
+}
+
+/// Single line of text typed by the user, displayed in the HUD.
+/// While a prompt is open, key presses are not handled as commands.
+#[derive(Debug)]
+pub struct TextPrompt {
+    pub kind: PromptKind,
+    pub text: String,
+}
+
+impl TextPrompt {
+    pub fn new(kind: PromptKind, text: String) -> Self {
+        TextPrompt { kind, text }
+    }
+
+    pub fn label(&self) -> String {
+        match self.kind {
+            PromptKind::AddSite => "Add site (x, y)".to_string(),
+            PromptKind::EditSite { site, .. } => format!("Move site {} to (x, y)", site),
+            PromptKind::Seed => "Seed".to_string(),
+            PromptKind::ImagePath => "Density image path".to_string(),
+            PromptKind::RegionPath => "Region file path".to_string(),
+            PromptKind::CurveSites => "Sites along curve (count, jitter)".to_string(),
+        }
+    }
+
+    /// Appends `c` to the text if it can be part of the expected input.
+    pub fn push(&mut self, c: char) {
+        let accepted = match self.kind {
+            PromptKind::AddSite | PromptKind::EditSite { .. } | PromptKind::CurveSites => c.is_ascii_digit() || ".,-+eE ".contains(c),
+            PromptKind::Seed => c.is_ascii_digit(),
+            PromptKind::ImagePath | PromptKind::RegionPath => !c.is_control(),
+        };
+
+        if accepted {
+            self.text.push(c);
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.text.pop();
+    }
+}
+
+/// Parses a point typed as `x, y`. Coordinates can be separated by a comma, whitespace or both.
+pub fn parse_point(text: &str) -> Option<Point> {
+    let mut coords = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>());
+
+    match (coords.next(), coords.next(), coords.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) if x.is_finite() && y.is_finite() => Some(Point { x, y }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text as the user types it, one character at a time.
+    fn typed(kind: PromptKind, text: &str) -> String {
+        let mut prompt = TextPrompt::new(kind, String::new());
+        for c in text.chars() {
+            prompt.push(c);
+        }
+        prompt.text
+    }
+
+    #[test]
+    fn parse_point_expect_point() {
+        let p = parse_point(&typed(PromptKind::AddSite, " -0.5, 1e-3")).unwrap();
+        assert_eq!(-0.5, p.x);
+        assert_eq!(0.001, p.y);
+
+        let p = parse_point(&typed(PromptKind::AddSite, "0.25 0.75")).unwrap();
+        assert_eq!(0.25, p.x);
+        assert_eq!(0.75, p.y);
+    }
+
+    #[test]
+    fn parse_point_expect_none() {
+        assert!(parse_point("").is_none());
+        assert!(parse_point("1.0").is_none());
+        assert!(parse_point("1.0, 2.0, 3.0").is_none());
+        assert!(parse_point("1.0, a").is_none());
+    }
+}
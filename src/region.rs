@@ -0,0 +1,231 @@
+use std::fs;
+use bevy::prelude::*;
+use rand::RngCore;
+use voronoice::Point;
+
+use super::{
+    utils::{self, distance},
+    ColorBundle,
+    State,
+    measure::{polygon_area, polygon_perimeter},
+    prompt::parse_point,
+    site_generators::{GeneratorInput, SiteGenerator},
+};
+
+/// Smallest area of a region relative to its squared perimeter; thinner polygons, such as collinear or self-cancelling vertices, are rejected.
+const MIN_RELATIVE_AREA: f64 = 1e-6;
+
+/// Smallest fraction of the bounding box a region is assumed to cover, so tiny regions do not ask generators for millions of sites.
+const MIN_COVERAGE: f64 = 0.01;
+
+/// Polygon sites are generated in, instead of the whole bounding box. It does not need to be convex.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub vertices: Vec<Point>,
+}
+
+impl Region {
+    /// Region with the given vertices, if there are at least three and they enclose an area.
+    pub fn new(vertices: Vec<Point>) -> Result<Self, String> {
+        if vertices.len() < 3 {
+            return Err("A region needs at least 3 vertices".to_string());
+        }
+
+        let (area, perimeter) = (polygon_area(&vertices), polygon_perimeter(&vertices));
+        if area.is_nan() || area <= MIN_RELATIVE_AREA * perimeter * perimeter {
+            return Err("A region must enclose an area".to_string());
+        }
+
+        Ok(Region { vertices })
+    }
+
+    /// Parses one vertex per line, as `x, y`. Empty lines and lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let vertices = text.lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| parse_point(line).ok_or_else(|| format!("Invalid vertex on line {}: {}", i + 1, line)))
+            .collect::<Result<Vec<Point>, String>>()?;
+
+        Region::new(vertices)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        Region::parse(&text)
+    }
+
+    /// Returns true if `p` is inside the polygon (even-odd rule).
+    pub fn contains(&self, p: &Point) -> bool {
+        let n = self.vertices.len();
+        let mut inside = false;
+        for i in 0..n {
+            let (a, b) = (&self.vertices[i], &self.vertices[(i + n - 1) % n]);
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (b.x - a.x) * (p.y - a.y) / (b.y - a.y) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    pub fn area(&self) -> f64 {
+        polygon_area(&self.vertices)
+    }
+
+    /// Sites `spacing` apart along the closed boundary, starting at the first vertex. Empty if `spacing` is not a positive number.
+    pub fn boundary_sites(&self, spacing: f64) -> Vec<Point> {
+        if spacing <= 0.0 || !spacing.is_finite() {
+            return Vec::new();
+        }
+
+        let n = self.vertices.len();
+        let mut sites = Vec::new();
+        // distance along the boundary to the next site, carried over from one edge to the next
+        let mut next = 0.0;
+        for i in 0..n {
+            let (a, b) = (&self.vertices[i], &self.vertices[(i + 1) % n]);
+            let length = distance(a, b);
+            while next < length {
+                let t = next / length;
+                sites.push(Point { x: a.x + t * (b.x - a.x), y: a.y + t * (b.y - a.y) });
+                next += spacing;
+            }
+            next -= length;
+        }
+        sites
+    }
+
+    /// Sites of `generator` inside the region, followed by sites along its boundary if `boundary` is set.
+    /// Generators are asked for more sites in proportion to the part of the bounding box the region covers, so about the requested number end up inside it.
+    pub fn generate(&self, generator: &dyn SiteGenerator, input: &GeneratorInput, rng: &mut dyn RngCore, boundary: bool) -> Vec<Point> {
+        let bounding_box_area = input.bounding_box.width() * input.bounding_box.height();
+        let coverage = (self.area() / bounding_box_area).max(MIN_COVERAGE).min(1.0);
+        let scaled = GeneratorInput {
+            size: (input.size as f64 / coverage).ceil() as usize,
+            ..*input
+        };
+
+        let mut sites: Vec<Point> = generator.generate(&scaled, rng)
+            .into_iter()
+            .filter(|p| self.contains(p))
+            .collect();
+
+        if boundary {
+            // twice as dense as the sites inside
+            let spacing = (self.area() / input.size.max(1) as f64).sqrt() / 2.0;
+            sites.extend(self.boundary_sites(spacing));
+        }
+
+        sites
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} vertices, area {:.4}", self.vertices.len(), self.area())
+    }
+}
+
+/// Region outline and the vertices of the region being drawn.
+pub struct RegionOverlay;
+
+const REGION_COLOR: Color = Color::ORANGE;
+const DRAWING_COLOR: Color = Color::GREEN;
+
+/// Redraws the region outline when it or the region being drawn changes.
+pub fn update_region_overlay(
+    state: Res<State>,
+    mut rendered: Local<Option<(u64, Vec<(f64, f64)>)>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<Entity, With<RegionOverlay>>) {
+
+    // the region only changes along with the diagram
+    let drawing = state.region_drawing.as_ref().map_or(Vec::new(), |v| v.iter().map(|p| (p.x, p.y)).collect());
+    let key = Some((state.generation, drawing));
+    if *rendered == key {
+        return;
+    }
+    *rendered = key;
+
+    for e in query.iter() {
+        commands.entity(e).despawn();
+    }
+
+    let transform = Transform::from_translation(Vec3::Y * state.render_layers.tool_height());
+    if let Some(region) = state.region.as_ref() {
+        commands
+            .spawn_bundle(ColorBundle {
+                mesh: meshes.add(utils::build_line_strip(&region.vertices, true, REGION_COLOR)),
+                transform,
+                ..Default::default()
+            })
+            .insert(RegionOverlay);
+    }
+
+    if let Some(vertices) = state.region_drawing.as_ref().filter(|v| v.len() > 1) {
+        commands
+            .spawn_bundle(ColorBundle {
+                mesh: meshes.add(utils::build_line_strip(vertices, false, DRAWING_COLOR)),
+                transform,
+                ..Default::default()
+            })
+            .insert(RegionOverlay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn l_shape() -> Region {
+        Region::new(vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 2.0, y: 0.0 },
+            Point { x: 2.0, y: 1.0 },
+            Point { x: 1.0, y: 1.0 },
+            Point { x: 1.0, y: 2.0 },
+            Point { x: 0.0, y: 2.0 },
+        ]).unwrap()
+    }
+
+    #[test]
+    fn contains_concave_polygon() {
+        let region = l_shape();
+        assert!(region.contains(&Point { x: 0.5, y: 1.5 }));
+        assert!(region.contains(&Point { x: 1.5, y: 0.5 }));
+        assert!(!region.contains(&Point { x: 1.5, y: 1.5 }));
+        assert!(!region.contains(&Point { x: -0.5, y: 0.5 }));
+    }
+
+    #[test]
+    fn boundary_sites_are_evenly_spaced() {
+        let sites = l_shape().boundary_sites(0.5);
+
+        // perimeter of 8
+        assert_eq!(16, sites.len());
+        assert_eq!(0.5, sites[1].x);
+        assert_eq!(1.0, sites[6].y);
+    }
+
+    #[test]
+    fn parse_expect_vertices_or_error() {
+        let region = Region::parse("# triangle\n0, 0\n\n1, 0\n0 1\n").unwrap();
+        assert_eq!(3, region.vertices.len());
+
+        assert!(Region::parse("0, 0\n1, 0").is_err());
+        assert!(Region::parse("0, 0\n1, 0\nx, 1").is_err());
+    }
+
+    #[test]
+    fn degenerate_regions_are_rejected() {
+        let collinear = vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 2.0, y: 0.0 }];
+        assert!(Region::new(collinear).is_err());
+
+        // both halves cancel out
+        let bow_tie = vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 1.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 0.0, y: 1.0 }];
+        assert!(Region::new(bow_tie).is_err());
+
+        assert!(l_shape().boundary_sites(0.0).is_empty());
+        assert!(l_shape().boundary_sites(f64::NAN).is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+use bevy::{prelude::*, render::pipeline::PrimitiveTopology};
+use voronoice::{Point, Voronoi};
+
+use super::{
+    utils,
+    color_white,
+    ColorBundle,
+    voronoi_mesh_generator::VoronoiMeshGenerator,
+};
+
+/// Height between consecutive layers, so a layer higher in the draw order is drawn over the ones below it.
+const LAYER_SPACING: f32 = 0.0005;
+
+const PALETTE: [(&str, Color); 9] = [
+    ("red", Color::RED),
+    ("white", Color::WHITE),
+    ("gray", Color::GRAY),
+    ("green", Color::GREEN),
+    ("blue", Color::BLUE),
+    ("yellow", Color::YELLOW),
+    ("cyan", Color::CYAN),
+    ("pink", Color::PINK),
+    ("orange", Color::ORANGE),
+];
+
+const OPACITIES: [f32; 4] = [1.0, 0.75, 0.5, 0.25];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayerKind {
+    Sites,
+    VoronoiEdges,
+    VoronoiFill,
+    DelaunayEdges,
+    DelaunayFill,
+    Hull,
+    BoundingBox,
+    /// Snapshot site layers, drawn in their own colors
+    Overlays,
+}
+
+/// Key toggling each layer.
+pub const RENDER_LAYER_KEYS: [(LayerKind, KeyCode); 8] = [
+    (LayerKind::Sites, KeyCode::F1),
+    (LayerKind::VoronoiEdges, KeyCode::F2),
+    (LayerKind::VoronoiFill, KeyCode::F3),
+    (LayerKind::DelaunayEdges, KeyCode::F4),
+    (LayerKind::DelaunayFill, KeyCode::F5),
+    (LayerKind::Hull, KeyCode::F6),
+    (LayerKind::BoundingBox, KeyCode::F7),
+    (LayerKind::Overlays, KeyCode::F8),
+];
+
+pub struct RenderLayer {
+    pub kind: LayerKind,
+    pub visible: bool,
+    /// Index in the palette
+    color: usize,
+    pub opacity: f32,
+}
+
+impl RenderLayer {
+    fn new(kind: LayerKind, visible: bool, color: usize, opacity: f32) -> Self {
+        RenderLayer {
+            kind,
+            visible,
+            color,
+            opacity,
+        }
+    }
+
+    /// Color of the layer with its opacity as alpha.
+    pub fn color(&self) -> Color {
+        let mut color = PALETTE[self.color].1;
+        color.set_a(self.opacity);
+        color
+    }
+
+    /// Bundle to draw the layer meshes with, blended if the layer is translucent.
+    pub fn bundle(&self) -> ColorBundle {
+        if self.opacity < 1.0 {
+            ColorBundle::translucent()
+        } else {
+            ColorBundle::default()
+        }
+    }
+
+    /// Builds the layer mesh for `voronoi`, if the layer is drawn from the diagram.
+    pub fn build_mesh(&self, voronoi: &Voronoi) -> Option<Mesh> {
+        let generator = |topology| VoronoiMeshGenerator { voronoi, coloring: color_white, topology };
+        let mut mesh = match self.kind {
+            LayerKind::Sites => generator(PrimitiveTopology::PointList).build_delauney_mesh(),
+            LayerKind::VoronoiEdges => generator(PrimitiveTopology::LineList).build_voronoi_mesh(),
+            LayerKind::VoronoiFill => generator(PrimitiveTopology::TriangleList).build_voronoi_mesh(),
+            LayerKind::DelaunayEdges => generator(PrimitiveTopology::LineList).build_delauney_mesh(),
+            LayerKind::DelaunayFill => generator(PrimitiveTopology::TriangleList).build_delauney_mesh(),
+            LayerKind::Hull => {
+                let hull: Vec<Point> = voronoi.triangulation().hull.iter().map(|s| voronoi.sites()[*s].clone()).collect();
+                utils::build_line_strip(&hull, true, Color::WHITE)
+            },
+            LayerKind::BoundingBox | LayerKind::Overlays => return None,
+        };
+
+        utils::set_mesh_color(&mut mesh, self.color());
+        Some(mesh)
+    }
+
+    pub fn describe(&self) -> String {
+        let color = if self.kind == LayerKind::Overlays { "own colors" } else { PALETTE[self.color].0 };
+        format!("{:?}: {}, {}, {:.0}%", self.kind, if self.visible { "on" } else { "off" }, color, self.opacity * 100.0)
+    }
+}
+
+/// Layers the diagram is drawn with, from the bottom to the top of the draw order.
+pub struct RenderLayers {
+    layers: Vec<RenderLayer>,
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers {
+            layers: vec![
+                RenderLayer::new(LayerKind::VoronoiFill, false, 0, 0.5),
+                RenderLayer::new(LayerKind::DelaunayFill, false, 1, 0.5),
+                RenderLayer::new(LayerKind::DelaunayEdges, true, 1, 1.0),
+                RenderLayer::new(LayerKind::VoronoiEdges, true, 0, 1.0),
+                RenderLayer::new(LayerKind::Hull, false, 5, 1.0),
+                RenderLayer::new(LayerKind::Sites, false, 1, 1.0),
+                RenderLayer::new(LayerKind::BoundingBox, false, 1, 1.0),
+                RenderLayer::new(LayerKind::Overlays, true, 0, 1.0),
+            ]
+        }
+    }
+}
+
+impl RenderLayers {
+    fn position(&self, kind: LayerKind) -> usize {
+        self.layers.iter().position(|l| l.kind == kind).unwrap()
+    }
+
+    pub fn get(&self, kind: LayerKind) -> &RenderLayer {
+        &self.layers[self.position(kind)]
+    }
+
+    fn get_mut(&mut self, kind: LayerKind) -> &mut RenderLayer {
+        let i = self.position(kind);
+        &mut self.layers[i]
+    }
+
+    /// Layers from the bottom to the top of the draw order.
+    pub fn iter(&self) -> impl Iterator<Item = &RenderLayer> {
+        self.layers.iter()
+    }
+
+    /// Height the layer is drawn at.
+    pub fn height(&self, kind: LayerKind) -> f32 {
+        self.position(kind) as f32 * LAYER_SPACING
+    }
+
+    /// Height of the tool overlays (diff, insertion preview, measurements, region and curve), above every layer.
+    pub fn tool_height(&self) -> f32 {
+        self.layers.len() as f32 * LAYER_SPACING
+    }
+
+    pub fn toggle(&mut self, kind: LayerKind) {
+        let layer = self.get_mut(kind);
+        layer.visible = !layer.visible;
+    }
+
+    /// Moves the layer one step up the draw order, or to the bottom if it is already at the top.
+    pub fn raise(&mut self, kind: LayerKind) {
+        let i = self.position(kind);
+        if i + 1 < self.layers.len() {
+            self.layers.swap(i, i + 1);
+        } else {
+            let layer = self.layers.remove(i);
+            self.layers.insert(0, layer);
+        }
+    }
+
+    pub fn next_color(&mut self, kind: LayerKind) {
+        let layer = self.get_mut(kind);
+        layer.color = (layer.color + 1) % PALETTE.len();
+    }
+
+    pub fn next_opacity(&mut self, kind: LayerKind) {
+        let layer = self.get_mut(kind);
+        let i = OPACITIES.iter().position(|o| *o == layer.opacity).unwrap_or(0);
+        layer.opacity = OPACITIES[(i + 1) % OPACITIES.len()];
+    }
+}
@@ -0,0 +1,942 @@
+use rand::{Rng, RngCore};
+use voronoice::{BoundingBox, Point};
+
+use super::{
+    density::DensityMap,
+    utils::{bounding_box_corners, distance},
+};
+
+/// Number of candidates tried around an active sample before it is retired (Bridson's `k`).
+const POISSON_DISK_ATTEMPTS: usize = 30;
+
+/// Smallest spacing between sites of the Poisson-disk, lattice and grid generators, relative to the bounding box size,
+/// so a single generation stays within a few hundred thousand sites.
+pub const MIN_SPACING: f64 = 0.002;
+
+/// How a generator parameter is stored and adjusted with the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamKind {
+    /// Positive length or exponent, scaled by 1.25 or 0.8
+    Scale,
+    /// Fraction between 0 and `max`, changed in steps of 0.05 so zero is reached exactly
+    Fraction { max: f64 },
+    /// Whole number, at least 1
+    Count,
+    /// Index in a list of named options
+    Choice(&'static [&'static str]),
+}
+
+/// Named, typed parameter of a [`SiteGenerator`].
+#[derive(Clone, Debug)]
+pub struct GeneratorParam {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    value: f64,
+}
+
+/// Fractions change by this much with each key press.
+const FRACTION_STEP: f64 = 0.05;
+
+fn step_fraction(value: f64, increase: bool, max: f64) -> f64 {
+    let steps = (value / FRACTION_STEP).round() + if increase { 1.0 } else { -1.0 };
+    (steps * FRACTION_STEP).max(0.0).min(max)
+}
+
+impl GeneratorParam {
+    pub fn scale(name: &'static str, value: f64) -> Self {
+        GeneratorParam { name, kind: ParamKind::Scale, value }
+    }
+
+    pub fn fraction(name: &'static str, value: f64, max: f64) -> Self {
+        GeneratorParam { name, kind: ParamKind::Fraction { max }, value }
+    }
+
+    pub fn count(name: &'static str, value: usize) -> Self {
+        GeneratorParam { name, kind: ParamKind::Count, value: value as f64 }
+    }
+
+    pub fn choice(name: &'static str, options: &'static [&'static str]) -> Self {
+        GeneratorParam { name, kind: ParamKind::Choice(options), value: 0.0 }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Value of a [`ParamKind::Count`] or [`ParamKind::Choice`] parameter.
+    pub fn index(&self) -> usize {
+        self.value as usize
+    }
+
+    pub fn adjust(&mut self, increase: bool) {
+        self.value = match self.kind {
+            ParamKind::Scale => self.value * if increase { 1.25 } else { 0.8 },
+            ParamKind::Fraction { max } => step_fraction(self.value, increase, max),
+            ParamKind::Count => if increase { self.value + 1.0 } else { (self.value - 1.0).max(1.0) },
+            // cycles through the options either way
+            ParamKind::Choice(options) => ((self.index() + if increase { 1 } else { options.len() - 1 }) % options.len()) as f64,
+        };
+    }
+
+    pub fn describe(&self) -> String {
+        match self.kind {
+            ParamKind::Scale | ParamKind::Fraction { .. } => format!("{}: {:.4}", self.name, self.value),
+            ParamKind::Count => format!("{}: {}", self.name, self.index()),
+            ParamKind::Choice(options) => format!("{}: {}", self.name, options[self.index()]),
+        }
+    }
+}
+
+/// Number of grid positions along a side of `length` and the offset that centers them on it.
+fn grid_axis(length: f64, spacing: f64) -> (usize, f64) {
+    let count = (length / spacing).floor() as usize + 1;
+    (count, (length - (count - 1) as f64 * spacing) / 2.0)
+}
+
+/// Triangular lattice filling the bounding box, whose Voronoi cells are regular hexagons.
+pub fn hex_lattice(bounding_box: &BoundingBox, spacing: f64) -> Vec<Point> {
+    let min = bounding_box_corners(bounding_box)[0].clone();
+    let spacing = spacing.max(MIN_SPACING * bounding_box.width().max(bounding_box.height()));
+    let row_spacing = spacing * 3f64.sqrt() / 2.0;
+    let (columns, x_offset) = grid_axis(bounding_box.width() - spacing / 2.0, spacing);
+    let (rows, y_offset) = grid_axis(bounding_box.height(), row_spacing);
+    let (x0, y0) = (min.x + x_offset, min.y + y_offset);
+
+    (0..rows)
+        .flat_map(|row| {
+            // every other row is shifted by half the spacing
+            let shift = if row % 2 == 1 { spacing / 2.0 } else { 0.0 };
+            (0..columns).map(move |column| Point {
+                x: x0 + shift + column as f64 * spacing,
+                y: y0 + row as f64 * row_spacing,
+            })
+        })
+        .collect()
+}
+
+/// Square grid filling the bounding box, with every site moved by up to `jitter` times the spacing along each axis.
+/// Without jitter every four neighboring sites are cocircular.
+pub fn jittered_grid<R: Rng + ?Sized>(bounding_box: &BoundingBox, spacing: f64, jitter: f64, rng: &mut R) -> Vec<Point> {
+    let min = bounding_box_corners(bounding_box)[0].clone();
+    let spacing = spacing.max(MIN_SPACING * bounding_box.width().max(bounding_box.height()));
+    let (columns, x_offset) = grid_axis(bounding_box.width(), spacing);
+    let (rows, y_offset) = grid_axis(bounding_box.height(), spacing);
+
+    let mut sites = Vec::with_capacity(rows * columns);
+    for row in 0..rows {
+        for column in 0..columns {
+            let mut site = Point { x: min.x + x_offset + column as f64 * spacing, y: min.y + y_offset + row as f64 * spacing };
+            if jitter > 0.0 {
+                site.x += rng.gen_range(-jitter..=jitter) * spacing;
+                site.y += rng.gen_range(-jitter..=jitter) * spacing;
+            }
+            sites.push(site);
+        }
+    }
+
+    sites
+}
+
+/// Standard normal sample (Box-Muller transform).
+fn gaussian<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+}
+
+/// `size` sites in Gaussian clusters around `cluster_count` centers placed uniformly in the bounding box.
+/// Sites are not clamped to the bounding box.
+pub fn gaussian_clusters<R: Rng + ?Sized>(bounding_box: &BoundingBox, size: usize, cluster_count: usize, spread: f64, rng: &mut R) -> Vec<Point> {
+    let min = bounding_box_corners(bounding_box)[0].clone();
+    let (width, height) = (bounding_box.width(), bounding_box.height());
+    let deviation = spread * width.max(height);
+    let centers: Vec<Point> = (0..cluster_count.max(1))
+        .map(|_| Point { x: min.x + rng.gen::<f64>() * width, y: min.y + rng.gen::<f64>() * height })
+        .collect();
+
+    (0..size)
+        .map(|_| {
+            let center = &centers[rng.gen_range(0..centers.len())];
+            Point { x: center.x + deviation * gaussian(rng), y: center.y + deviation * gaussian(rng) }
+        })
+        .collect()
+}
+
+/// Site at `radius` times `r` from the center of the bounding box, in a uniformly random direction, where `radius` is half the shortest bounding box side.
+fn polar_site<R: Rng + ?Sized>(bounding_box: &BoundingBox, r: f64, rng: &mut R) -> Point {
+    let center = bounding_box.center();
+    let radius = bounding_box.width().min(bounding_box.height()) / 2.0;
+    let angle = rng.gen::<f64>() * std::f64::consts::TAU;
+    Point { x: center.x + radius * r * angle.cos(), y: center.y + radius * r * angle.sin() }
+}
+
+/// `size` sites in the disk inscribed in the bounding box, at a distance from the center that follows `u^falloff` for uniform `u`.
+pub fn radial_falloff<R: Rng + ?Sized>(bounding_box: &BoundingBox, size: usize, falloff: f64, rng: &mut R) -> Vec<Point> {
+    (0..size)
+        .map(|_| {
+            let r = rng.gen::<f64>().powf(falloff);
+            polar_site(bounding_box, r, rng)
+        })
+        .collect()
+}
+
+/// `size` sites uniformly distributed in a ring inscribed in the bounding box, with `inner_radius` relative to the outer radius.
+pub fn annulus<R: Rng + ?Sized>(bounding_box: &BoundingBox, size: usize, inner_radius: f64, rng: &mut R) -> Vec<Point> {
+    let inner = inner_radius * inner_radius;
+    (0..size)
+        .map(|_| {
+            // uniform in area between the two circles
+            let r = (inner + (1.0 - inner) * rng.gen::<f64>()).sqrt();
+            polar_site(bounding_box, r, rng)
+        })
+        .collect()
+}
+
+/// Low-discrepancy sequences, which cover the unit square more evenly than random sampling.
+#[derive(Clone, Copy, Debug)]
+pub enum Sequence {
+    Halton,
+    Sobol,
+    R2,
+}
+
+/// Van der Corput radical inverse of `index` in `base`.
+fn radical_inverse(mut index: u32, base: u32) -> f64 {
+    let mut result = 0.0;
+    let mut fraction = 1.0 / base as f64;
+    while index > 0 {
+        result += (index % base) as f64 * fraction;
+        index /= base;
+        fraction /= base as f64;
+    }
+    result
+}
+
+/// Second dimension of the Sobol sequence, from the primitive polynomial `x + 1`.
+fn sobol_second_dimension(index: u32) -> f64 {
+    let mut result = 0u32;
+    // direction numbers m_i = 2 m_(i-1) xor m_(i-1), starting at m_1 = 1
+    let mut m = 1u32;
+    for bit in 0..32 {
+        if index & (1 << bit) != 0 {
+            result ^= m << (31 - bit);
+        }
+        m = (m << 1) ^ m;
+    }
+    result as f64 / 2f64.powi(32)
+}
+
+/// Point `index` of the sequence, in the unit square.
+fn sequence_point(sequence: Sequence, index: u32) -> (f64, f64) {
+    match sequence {
+        Sequence::Halton => (radical_inverse(index, 2), radical_inverse(index, 3)),
+        // the first Sobol dimension is the base 2 radical inverse
+        Sequence::Sobol => (radical_inverse(index, 2), sobol_second_dimension(index)),
+        Sequence::R2 => {
+            // inverse powers of the plastic number
+            const G: f64 = 1.324_717_957_244_746;
+            let n = index as f64;
+            ((0.5 + n / G).fract(), (0.5 + n / (G * G)).fract())
+        },
+    }
+}
+
+/// First `size` points of a low-discrepancy sequence, scaled to the bounding box. The point at the origin of the sequence is skipped.
+pub fn low_discrepancy(bounding_box: &BoundingBox, size: usize, sequence: Sequence) -> Vec<Point> {
+    let min = bounding_box_corners(bounding_box)[0].clone();
+    (1..=size as u32)
+        .map(|i| {
+            let (u, v) = sequence_point(sequence, i);
+            Point { x: min.x + u * bounding_box.width(), y: min.y + v * bounding_box.height() }
+        })
+        .collect()
+}
+
+/// Inputs that are known to be hard on Delaunay triangulation code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StressCase {
+    Collinear,
+    CocircularWithNoise,
+    Duplicates,
+    BoundingBoxEdges,
+    HugeMagnitude,
+    TinyMagnitude,
+    FarOutside,
+}
+
+impl StressCase {
+    pub const ALL: [StressCase; 7] = [
+        StressCase::Collinear,
+        StressCase::CocircularWithNoise,
+        StressCase::Duplicates,
+        StressCase::BoundingBoxEdges,
+        StressCase::HugeMagnitude,
+        StressCase::TinyMagnitude,
+        StressCase::FarOutside,
+    ];
+}
+
+/// Names of [`StressCase::ALL`], in the same order.
+const STRESS_CASE_NAMES: [&str; 7] = ["collinear", "cocircular with noise", "duplicates", "bounding box edges", "huge magnitude", "tiny magnitude", "far outside"];
+
+fn uniform_sites<R: Rng + ?Sized>(bounding_box: &BoundingBox, size: usize, rng: &mut R) -> Vec<Point> {
+    let min = bounding_box_corners(bounding_box)[0].clone();
+    (0..size)
+        .map(|_| Point { x: min.x + rng.gen::<f64>() * bounding_box.width(), y: min.y + rng.gen::<f64>() * bounding_box.height() })
+        .collect()
+}
+
+/// About `size` sites of the given degenerate configuration.
+pub fn stress_sites<R: Rng + ?Sized>(bounding_box: &BoundingBox, size: usize, case: StressCase, rng: &mut R) -> Vec<Point> {
+    let size = size.max(4);
+    let center = bounding_box.center().clone();
+    let min = bounding_box_corners(bounding_box)[0].clone();
+    let (width, height) = (bounding_box.width(), bounding_box.height());
+
+    match case {
+        StressCase::Collinear => (0..size)
+            // along the bounding box diagonal
+            .map(|i| {
+                let t = i as f64 / (size - 1) as f64;
+                Point { x: min.x + t * width, y: min.y + t * height }
+            })
+            .collect(),
+
+        StressCase::CocircularWithNoise => {
+            let radius = 0.8 * width.min(height) / 2.0;
+            (0..size)
+                .map(|i| {
+                    let angle = i as f64 * std::f64::consts::TAU / size as f64;
+                    let r = radius * (1.0 + 1e-12 * rng.gen_range(-1.0..1.0));
+                    Point { x: center.x + r * angle.cos(), y: center.y + r * angle.sin() }
+                })
+                .collect()
+        },
+
+        StressCase::Duplicates => {
+            // every site is repeated exactly and then nudged by a few units in the last place of its coordinates
+            let mut sites = uniform_sites(bounding_box, size / 3, rng);
+            let nudge = 4.0 * f64::EPSILON;
+            let copies: Vec<Point> = sites.iter()
+                .flat_map(|s| vec![s.clone(), Point { x: s.x * (1.0 + nudge), y: s.y * (1.0 - nudge) }])
+                .collect();
+            sites.extend(copies);
+            sites
+        },
+
+        StressCase::BoundingBoxEdges => {
+            let per_edge = size / 4;
+            let mut sites = vec![
+                Point { x: min.x, y: min.y },
+                Point { x: min.x + width, y: min.y },
+                Point { x: min.x + width, y: min.y + height },
+                Point { x: min.x, y: min.y + height },
+            ];
+            for i in 1..per_edge {
+                let t = i as f64 / per_edge as f64;
+                sites.push(Point { x: min.x + t * width, y: min.y });
+                sites.push(Point { x: min.x + width, y: min.y + t * height });
+                sites.push(Point { x: min.x + t * width, y: min.y + height });
+                sites.push(Point { x: min.x, y: min.y + t * height });
+            }
+            sites
+        },
+
+        StressCase::HugeMagnitude => uniform_sites(bounding_box, size, rng).into_iter()
+            .map(|s| Point { x: center.x + (s.x - center.x) * 1e12, y: center.y + (s.y - center.y) * 1e12 })
+            .collect(),
+
+        StressCase::TinyMagnitude => uniform_sites(bounding_box, size, rng).into_iter()
+            .map(|s| Point { x: center.x + (s.x - center.x) * 1e-12, y: center.y + (s.y - center.y) * 1e-12 })
+            .collect(),
+
+        StressCase::FarOutside => {
+            let mut sites = uniform_sites(bounding_box, size - 3, rng);
+            sites.push(Point { x: center.x + 1e6 * width, y: center.y });
+            sites.push(Point { x: center.x - 1e3 * width, y: center.y + 1e3 * height });
+            sites.push(Point { x: center.x, y: center.y - 1e9 * height });
+            sites
+        },
+    }
+}
+
+/// Blue-noise sites filling the bounding box, no two closer than `min_distance` (Bridson's Poisson-disk sampling).
+pub fn poisson_disk<R: Rng + ?Sized>(bounding_box: &BoundingBox, min_distance: f64, rng: &mut R) -> Vec<Point> {
+    let min = bounding_box_corners(bounding_box)[0].clone();
+    let (width, height) = (bounding_box.width(), bounding_box.height());
+    let min_distance = min_distance.max(MIN_SPACING * width.max(height));
+
+    // each grid cell fits at most one sample
+    let cell_size = min_distance / std::f64::consts::SQRT_2;
+    let columns = (width / cell_size).ceil().max(1.0) as usize;
+    let rows = (height / cell_size).ceil().max(1.0) as usize;
+    let mut grid: Vec<Option<usize>> = vec![None; columns * rows];
+    let cell_of = |p: &Point| {
+        let column = (((p.x - min.x) / cell_size) as usize).min(columns - 1);
+        let row = (((p.y - min.y) / cell_size) as usize).min(rows - 1);
+        (column, row)
+    };
+
+    let first = Point { x: min.x + rng.gen::<f64>() * width, y: min.y + rng.gen::<f64>() * height };
+    let (column, row) = cell_of(&first);
+    grid[row * columns + column] = Some(0);
+    let mut sites = vec![first];
+    let mut active = vec![0];
+
+    while !active.is_empty() {
+        let active_index = rng.gen_range(0..active.len());
+        let origin = sites[active[active_index]].clone();
+
+        let mut found = false;
+        for _ in 0..POISSON_DISK_ATTEMPTS {
+            // uniform sample in the annulus between min_distance and twice it
+            let r = min_distance * (1.0 + 3.0 * rng.gen::<f64>()).sqrt();
+            let angle = rng.gen::<f64>() * std::f64::consts::TAU;
+            let candidate = Point { x: origin.x + r * angle.cos(), y: origin.y + r * angle.sin() };
+
+            if candidate.x < min.x || candidate.x >= min.x + width || candidate.y < min.y || candidate.y >= min.y + height {
+                continue;
+            }
+
+            let (column, row) = cell_of(&candidate);
+            let far_enough = (row.saturating_sub(2)..(row + 3).min(rows))
+                .flat_map(|r| (column.saturating_sub(2)..(column + 3).min(columns)).map(move |c| r * columns + c))
+                .filter_map(|i| grid[i])
+                .all(|s| distance(&sites[s], &candidate) >= min_distance);
+
+            if far_enough {
+                grid[row * columns + column] = Some(sites.len());
+                active.push(sites.len());
+                sites.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(active_index);
+        }
+    }
+
+    sites
+}
+
+/// Uniform sites in a box twice the size of the bounding box, centered at the origin, so some of them are outside the bounding box.
+pub fn random_sites<R: Rng + ?Sized>(bounding_box: &BoundingBox, size: usize, rng: &mut R) -> Vec<Point> {
+    let x_range = rand::distributions::Uniform::new(-bounding_box.width(), bounding_box.width());
+    let y_range = rand::distributions::Uniform::new(-bounding_box.height(), bounding_box.height());
+
+    (0..size)
+        .map(|_| Point { x: rng.sample(x_range), y: rng.sample(y_range) })
+        .collect()
+}
+
+/// Center of the bounding box followed by `size` sites evenly spaced on a circle of `radius` around it.
+pub fn circle(bounding_box: &BoundingBox, size: usize, radius: f64) -> Vec<Point> {
+    let center = bounding_box.center().clone();
+    let step = std::f64::consts::TAU / size.max(1) as f64;
+    let mut sites = vec![center.clone()];
+    sites.extend((0..size).map(|i| {
+        let angle = i as f64 * step;
+        Point { x: center.x + radius * angle.cos(), y: center.y + radius * angle.sin() }
+    }));
+    sites
+}
+
+/// What sites are generated for.
+pub struct GeneratorInput<'a> {
+    pub bounding_box: &'a BoundingBox,
+    /// Number of sites requested; generators that fill the bounding box at a given spacing ignore it
+    pub size: usize,
+    /// Darkness of the loaded density image, if any
+    pub density_map: Option<&'a DensityMap>,
+}
+
+/// Distribution of sites, with named parameters that can be tuned from the keyboard.
+pub trait SiteGenerator: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn params(&self) -> &[GeneratorParam] {
+        &[]
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut []
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point>;
+
+    /// Whether sites follow the density image, so Lloyd relaxation should be weighted by it.
+    fn uses_density(&self) -> bool {
+        false
+    }
+}
+
+pub const RANDOM_GENERATOR: &str = "Random";
+pub const IMAGE_GENERATOR: &str = "Image";
+pub const STRESS_GENERATOR: &str = "Stress";
+
+struct Random;
+
+impl SiteGenerator for Random {
+    fn name(&self) -> &'static str {
+        RANDOM_GENERATOR
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        random_sites(input.bounding_box, input.size, rng)
+    }
+}
+
+struct Circle {
+    params: [GeneratorParam; 1],
+}
+
+impl SiteGenerator for Circle {
+    fn name(&self) -> &'static str {
+        "Circle"
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, _rng: &mut dyn RngCore) -> Vec<Point> {
+        let [radius] = &self.params;
+        circle(input.bounding_box, input.size, radius.value())
+    }
+}
+
+/// Square grid with about the requested number of sites.
+struct Square;
+
+impl SiteGenerator for Square {
+    fn name(&self) -> &'static str {
+        "Square"
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        let area = input.bounding_box.width() * input.bounding_box.height();
+        let spacing = (area / input.size.max(1) as f64).sqrt();
+        jittered_grid(input.bounding_box, spacing, 0.0, rng)
+    }
+}
+
+struct PoissonDisk {
+    params: [GeneratorParam; 1],
+}
+
+impl SiteGenerator for PoissonDisk {
+    fn name(&self) -> &'static str {
+        "Poisson disk"
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        let [min_distance] = &self.params;
+        poisson_disk(input.bounding_box, min_distance.value(), rng)
+    }
+}
+
+struct HexLattice {
+    params: [GeneratorParam; 1],
+}
+
+impl SiteGenerator for HexLattice {
+    fn name(&self) -> &'static str {
+        "Hex lattice"
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, _rng: &mut dyn RngCore) -> Vec<Point> {
+        let [spacing] = &self.params;
+        hex_lattice(input.bounding_box, spacing.value())
+    }
+}
+
+struct JitteredGrid {
+    params: [GeneratorParam; 2],
+}
+
+impl SiteGenerator for JitteredGrid {
+    fn name(&self) -> &'static str {
+        "Jittered grid"
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        let [jitter, spacing] = &self.params;
+        jittered_grid(input.bounding_box, spacing.value(), jitter.value(), rng)
+    }
+}
+
+struct GaussianClusters {
+    params: [GeneratorParam; 2],
+}
+
+impl SiteGenerator for GaussianClusters {
+    fn name(&self) -> &'static str {
+        "Gaussian clusters"
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        let [cluster_count, spread] = &self.params;
+        gaussian_clusters(input.bounding_box, input.size, cluster_count.index(), spread.value(), rng)
+    }
+}
+
+struct RadialFalloff {
+    params: [GeneratorParam; 1],
+}
+
+impl SiteGenerator for RadialFalloff {
+    fn name(&self) -> &'static str {
+        "Radial falloff"
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        let [falloff] = &self.params;
+        radial_falloff(input.bounding_box, input.size, falloff.value(), rng)
+    }
+}
+
+struct Annulus {
+    params: [GeneratorParam; 1],
+}
+
+impl SiteGenerator for Annulus {
+    fn name(&self) -> &'static str {
+        "Annulus"
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        let [inner_radius] = &self.params;
+        annulus(input.bounding_box, input.size, inner_radius.value(), rng)
+    }
+}
+
+struct LowDiscrepancy(Sequence);
+
+impl SiteGenerator for LowDiscrepancy {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            Sequence::Halton => "Halton",
+            Sequence::Sobol => "Sobol",
+            Sequence::R2 => "R2",
+        }
+    }
+
+    fn generate(&self, input: &GeneratorInput, _rng: &mut dyn RngCore) -> Vec<Point> {
+        low_discrepancy(input.bounding_box, input.size, self.0)
+    }
+}
+
+/// Density proportional to the darkness of the loaded image, uniform until one is loaded.
+struct Image;
+
+impl SiteGenerator for Image {
+    fn name(&self) -> &'static str {
+        IMAGE_GENERATOR
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        match input.density_map {
+            Some(map) => map.sample(input.bounding_box, input.size, rng),
+            None => random_sites(input.bounding_box, input.size, rng),
+        }
+    }
+
+    fn uses_density(&self) -> bool {
+        true
+    }
+}
+
+struct Stress {
+    params: [GeneratorParam; 1],
+}
+
+impl SiteGenerator for Stress {
+    fn name(&self) -> &'static str {
+        STRESS_GENERATOR
+    }
+
+    fn params(&self) -> &[GeneratorParam] {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut [GeneratorParam] {
+        &mut self.params
+    }
+
+    fn generate(&self, input: &GeneratorInput, rng: &mut dyn RngCore) -> Vec<Point> {
+        let [case] = &self.params;
+        stress_sites(input.bounding_box, input.size, StressCase::ALL[case.index()], rng)
+    }
+}
+
+/// Every site generator, in the order they are cycled through, and the one in use.
+pub struct GeneratorRegistry {
+    generators: Vec<Box<dyn SiteGenerator>>,
+    selected: usize,
+    /// Index in the selected generator parameters of the one adjusted with the keyboard
+    selected_param: usize,
+}
+
+impl Default for GeneratorRegistry {
+    fn default() -> Self {
+        GeneratorRegistry {
+            generators: vec![
+                Box::new(Random),
+                Box::new(Circle { params: [GeneratorParam::scale("radius", 1.0)] }),
+                Box::new(Square),
+                Box::new(PoissonDisk { params: [GeneratorParam::scale("min distance", 0.05)] }),
+                Box::new(HexLattice { params: [GeneratorParam::scale("spacing", 0.1)] }),
+                Box::new(JitteredGrid { params: [GeneratorParam::fraction("jitter", 0.0, 1.0), GeneratorParam::scale("spacing", 0.1)] }),
+                Box::new(GaussianClusters { params: [GeneratorParam::count("clusters", 5), GeneratorParam::scale("spread", 0.05)] }),
+                Box::new(RadialFalloff { params: [GeneratorParam::scale("falloff", 2.0)] }),
+                Box::new(Annulus { params: [GeneratorParam::fraction("inner radius", 0.6, 0.95)] }),
+                Box::new(LowDiscrepancy(Sequence::Halton)),
+                Box::new(LowDiscrepancy(Sequence::Sobol)),
+                Box::new(LowDiscrepancy(Sequence::R2)),
+                Box::new(Image),
+                Box::new(Stress { params: [GeneratorParam::choice("case", &STRESS_CASE_NAMES)] }),
+            ],
+            selected: 0,
+            selected_param: 0,
+        }
+    }
+}
+
+impl GeneratorRegistry {
+    pub fn selected(&self) -> &dyn SiteGenerator {
+        self.generators[self.selected].as_ref()
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.generators.len();
+        self.selected_param = 0;
+    }
+
+    /// Selects the generator with the given name, if there is one.
+    pub fn select(&mut self, name: &str) {
+        if let Some(i) = self.generators.iter().position(|g| g.name() == name) {
+            self.selected = i;
+            self.selected_param = 0;
+        }
+    }
+
+    /// Selects the generator called `name`, or steps its first parameter if it is already selected.
+    pub fn select_or_cycle(&mut self, name: &str) {
+        if self.selected().name() == name {
+            self.selected_param = 0;
+            self.adjust_param(true);
+        } else {
+            self.select(name);
+        }
+    }
+
+    pub fn next_param(&mut self) {
+        let count = self.selected().params().len();
+        self.selected_param = (self.selected_param + 1) % count.max(1);
+    }
+
+    /// Adjusts the selected parameter of the selected generator. Returns false if it has no parameters.
+    pub fn adjust_param(&mut self, increase: bool) -> bool {
+        let selected_param = self.selected_param;
+        if let Some(param) = self.generators[self.selected].params_mut().get_mut(selected_param) {
+            param.adjust(increase);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Name of the selected generator and its parameters, with the selected one marked.
+    pub fn describe(&self) -> String {
+        let generator = self.selected();
+        let params: String = generator.params().iter().enumerate()
+            .map(|(i, p)| format!(", {}{}", if i == self.selected_param { ">" } else { "" }, p.describe()))
+            .collect();
+        format!("{}{}", generator.name(), params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn poisson_disk_keeps_min_distance_inside_bounding_box() {
+        let bounding_box = BoundingBox::new_centered_square(2.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let sites = poisson_disk(&bounding_box, 0.1, &mut rng);
+
+        // a 2x2 box fits a few hundred samples 0.1 apart
+        assert!(sites.len() > 200);
+        for (i, a) in sites.iter().enumerate() {
+            assert!(a.x >= -1.0 && a.x < 1.0 && a.y >= -1.0 && a.y < 1.0);
+            for b in sites.iter().skip(i + 1) {
+                assert!(distance(a, b) >= 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn jittered_grid_without_jitter_is_square_grid() {
+        let bounding_box = BoundingBox::new_centered_square(2.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let sites = jittered_grid(&bounding_box, 0.5, 0.0, &mut rng);
+
+        assert_eq!(25, sites.len());
+        assert_eq!(-1.0, sites[0].x);
+        assert_eq!(-1.0, sites[0].y);
+        assert_eq!(-0.5, sites[1].x);
+        assert_eq!(1.0, sites[24].y);
+    }
+
+    #[test]
+    fn annulus_sites_are_inside_ring() {
+        let bounding_box = BoundingBox::new_centered_square(2.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let sites = annulus(&bounding_box, 500, 0.5, &mut rng);
+
+        assert_eq!(500, sites.len());
+        for s in sites.iter() {
+            let r = (s.x * s.x + s.y * s.y).sqrt();
+            assert!(r >= 0.5 - 1e-12 && r <= 1.0 + 1e-12);
+        }
+    }
+
+    #[test]
+    fn low_discrepancy_sequences_start_with_known_points() {
+        assert_eq!((0.5, 1.0 / 3.0), sequence_point(Sequence::Halton, 1));
+        assert_eq!((0.25, 2.0 / 3.0), sequence_point(Sequence::Halton, 2));
+
+        assert_eq!((0.5, 0.5), sequence_point(Sequence::Sobol, 1));
+        assert_eq!((0.25, 0.75), sequence_point(Sequence::Sobol, 2));
+        assert_eq!((0.75, 0.25), sequence_point(Sequence::Sobol, 3));
+    }
+
+    #[test]
+    fn adjust_jitter_reaches_zero() {
+        let mut jitter = GeneratorParam::fraction("jitter", 0.1, 1.0);
+        jitter.adjust(false);
+        jitter.adjust(false);
+        jitter.adjust(false);
+        assert_eq!(0.0, jitter.value());
+    }
+
+    #[test]
+    fn adjust_choice_wraps_around() {
+        let mut case = GeneratorParam::choice("case", &STRESS_CASE_NAMES);
+        case.adjust(false);
+        assert_eq!(StressCase::ALL.len() - 1, case.index());
+        case.adjust(true);
+        assert_eq!(0, case.index());
+    }
+
+    #[test]
+    fn stress_sites_expect_degenerate_shapes() {
+        let bounding_box = BoundingBox::new_centered_square(2.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sites = |case| stress_sites(&bounding_box, 100, case, &mut rng);
+        let inside = |s: &Point| s.x.abs() <= 1.0 && s.y.abs() <= 1.0;
+
+        let collinear = sites(StressCase::Collinear);
+        assert_eq!(100, collinear.len());
+        assert!(collinear.iter().all(|s| s.x == s.y && inside(s)));
+
+        let cocircular = sites(StressCase::CocircularWithNoise);
+        assert!(cocircular.iter().all(|s| (distance(s, &Point { x: 0.0, y: 0.0 }) - 0.8).abs() < 1e-10));
+
+        // each original site is followed by an exact copy and a copy a few units in the last place away
+        let duplicates = sites(StressCase::Duplicates);
+        let originals = duplicates.len() / 3;
+        for (i, s) in duplicates[..originals].iter().enumerate() {
+            let (copy, nudged) = (&duplicates[originals + 2 * i], &duplicates[originals + 2 * i + 1]);
+            assert!(copy.x == s.x && copy.y == s.y);
+            assert!((nudged.x != s.x || nudged.y != s.y) && distance(nudged, s) < 1e-14);
+        }
+
+        let edges = sites(StressCase::BoundingBoxEdges);
+        assert!(edges.iter().all(|s| s.x.abs() == 1.0 || s.y.abs() == 1.0));
+        assert!(edges.iter().all(inside));
+
+        assert!(sites(StressCase::HugeMagnitude).iter().any(|s| s.x.abs() > 1e10 || s.y.abs() > 1e10));
+        assert!(sites(StressCase::TinyMagnitude).iter().all(|s| s.x.abs() < 1e-11 && s.y.abs() < 1e-11));
+        assert_eq!(3, sites(StressCase::FarOutside).iter().filter(|s| !inside(s)).count());
+    }
+
+    #[test]
+    fn registry_select_resets_selected_param() {
+        let mut registry = GeneratorRegistry::default();
+        registry.select("Jittered grid");
+        registry.next_param();
+        assert!(registry.adjust_param(true));
+        assert_eq!("Jittered grid, jitter: 0.0000, >spacing: 0.1250", registry.describe());
+
+        registry.select(STRESS_GENERATOR);
+        assert_eq!("Stress, >case: collinear", registry.describe());
+    }
+
+    #[test]
+    fn registry_select_or_cycle_steps_first_param() {
+        let mut registry = GeneratorRegistry::default();
+        registry.select_or_cycle(STRESS_GENERATOR);
+        assert_eq!(0, registry.selected().params()[0].index());
+
+        registry.select_or_cycle(STRESS_GENERATOR);
+        assert_eq!(1, registry.selected().params()[0].index());
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use voronoice::{BoundingBox, ClipBehavior, Point, Voronoi, VoronoiBuilder};
+
+use super::{
+    utils,
+    build_voronoi,
+    get_circle,
+    next_clip_behavior,
+    ColorBundle,
+    Mouse,
+    State,
+    camera,
+};
+
+/// Lloyd relaxation iterations cycled through for the right pane.
+const LLOYD_ITERATIONS: [usize; 5] = [0, 1, 2, 5, 10];
+/// Space between the panes, relative to their width.
+const PANE_GAP: f32 = 0.1;
+
+/// Second diagram built from the same sites with different builder options, shown to the right of the main one.
+/// Both panes are drawn in the same scene under the one camera, not in separate viewports, so panning and zooming always move them together.
+pub struct SplitView {
+    pub clip_behavior: ClipBehavior,
+    pub lloyd_iterations: usize,
+    pub voronoi: Option<Voronoi>,
+    /// Translation from the main diagram to the right pane
+    pub offset: Vec3,
+    /// Position along the screen right axis where the right pane starts
+    divider: f32,
+}
+
+/// Marks where the cursor is in the pane it is not over.
+pub struct SplitCursor;
+
+impl SplitView {
+    pub fn new(clip_behavior: ClipBehavior) -> Self {
+        SplitView {
+            clip_behavior,
+            lloyd_iterations: 0,
+            voronoi: None,
+            offset: Vec3::ZERO,
+            divider: f32::MAX,
+        }
+    }
+
+    pub fn cycle_clip_behavior(&mut self) {
+        self.clip_behavior = next_clip_behavior(self.clip_behavior);
+    }
+
+    pub fn cycle_lloyd_iterations(&mut self) {
+        let i = LLOYD_ITERATIONS.iter().position(|i| *i == self.lloyd_iterations).unwrap_or(0);
+        self.lloyd_iterations = LLOYD_ITERATIONS[(i + 1) % LLOYD_ITERATIONS.len()];
+    }
+
+    /// Builds the right pane diagram and places it next to the area covered by `sites` and `bounding_box`.
+    /// `builder` is configured like the main diagram's, so the panes only differ in the options set here.
+    pub fn rebuild(&mut self, builder: VoronoiBuilder, sites: Vec<Point>, bounding_box: &BoundingBox) {
+        let right = camera::top_down_rotation() * Vec3::X;
+
+        let corners = utils::bounding_box_corners(bounding_box);
+        let (min, max) = sites.iter().chain(corners.iter())
+            .map(|p| Vec3::from(utils::point_to_f32_vec(p)).dot(right))
+            .fold((f32::MAX, f32::MIN), |(min, max), x| (min.min(x), max.max(x)));
+
+        let width = (max - min) * (1.0 + PANE_GAP);
+        self.offset = right * width;
+        self.divider = (min + max) / 2.0 + width / 2.0;
+
+        self.voronoi = build_voronoi(builder
+            .set_clip_behavior(self.clip_behavior)
+            .set_lloyd_relaxation_iterations(self.lloyd_iterations)
+            .set_sites(sites));
+    }
+
+    /// Offset of the pane containing `world_pos`.
+    pub fn pane_offset(&self, world_pos: Vec3) -> Vec3 {
+        if world_pos.dot(camera::top_down_rotation() * Vec3::X) > self.divider {
+            self.offset
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{:?}, {} Lloyd iterations", self.clip_behavior, self.lloyd_iterations)
+    }
+}
+
+pub fn setup_split_cursor(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.spawn_bundle(ColorBundle {
+            mesh: meshes.add(get_circle(16)),
+            visible: Visible { is_visible: false, ..Default::default() },
+            transform: Transform::from_scale(Vec3::splat(0.02)),
+            ..Default::default()
+        })
+        .insert(SplitCursor);
+}
+
+/// Mirrors the cursor in the pane it is not over, so the same spot can be compared in both diagrams.
+pub fn update_split_cursor(
+    state: Res<State>,
+    mouse_query: Query<&Mouse>,
+    mut query: Query<(&mut Transform, &mut Visible), With<SplitCursor>>) {
+
+    let mouse = mouse_query.iter().next().unwrap();
+    for (mut transform, mut visible) in query.iter_mut() {
+        if let Some(split) = state.split.as_ref().filter(|_| mouse.on_plane) {
+            transform.translation = mouse.world_pos + split.offset - mouse.pane_offset;
+            visible.is_visible = true;
+        } else {
+            visible.is_visible = false;
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use voronoice::Point;
+
+/// Copies below this distance from each other are considered the same site.
+const SAME_SITE_EPSILON: f64 = 1e-9;
+
+/// Maximum number of copies in rotational symmetry.
+pub const MAX_ROTATIONAL_FOLD: u32 = 12;
+
+/// Symmetry applied when placing a site with the mouse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    None,
+    /// Mirror across the horizontal (X) axis through the center
+    MirrorX,
+    /// Mirror across the vertical (Y) axis through the center
+    MirrorY,
+    /// Mirror across both axes through the center
+    MirrorXY,
+    /// N-fold rotation around the center
+    Rotational(u32),
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Symmetry::None
+    }
+}
+
+impl Symmetry {
+    /// Cycles through the mirror modes.
+    pub fn next_mirror(self) -> Self {
+        match self {
+            Symmetry::None => Symmetry::MirrorX,
+            Symmetry::MirrorX => Symmetry::MirrorY,
+            Symmetry::MirrorY => Symmetry::MirrorXY,
+            Symmetry::MirrorXY => Symmetry::None,
+            Symmetry::Rotational(_) => Symmetry::MirrorX,
+        }
+    }
+
+    /// Adds one copy to rotational symmetry, wrapping back to no symmetry after [`MAX_ROTATIONAL_FOLD`].
+    pub fn increase_fold(self) -> Self {
+        match self {
+            Symmetry::Rotational(n) if n >= MAX_ROTATIONAL_FOLD => Symmetry::None,
+            Symmetry::Rotational(n) => Symmetry::Rotational(n + 1),
+            _ => Symmetry::Rotational(2),
+        }
+    }
+
+    /// Removes one copy from rotational symmetry.
+    pub fn decrease_fold(self) -> Self {
+        match self {
+            Symmetry::Rotational(n) if n > 2 => Symmetry::Rotational(n - 1),
+            Symmetry::Rotational(_) => Symmetry::None,
+            _ => Symmetry::Rotational(MAX_ROTATIONAL_FOLD),
+        }
+    }
+
+    /// Returns `point` and its symmetric copies around `center`. Copies that coincide (e.g. a point on a mirror axis) are returned once.
+    pub fn images(&self, point: &Point, center: &Point) -> Vec<Point> {
+        let mirror_x = |p: &Point| Point { x: p.x, y: 2.0 * center.y - p.y };
+        let mirror_y = |p: &Point| Point { x: 2.0 * center.x - p.x, y: p.y };
+
+        let images = match self {
+            Symmetry::None => vec![point.clone()],
+            Symmetry::MirrorX => vec![point.clone(), mirror_x(point)],
+            Symmetry::MirrorY => vec![point.clone(), mirror_y(point)],
+            Symmetry::MirrorXY => vec![point.clone(), mirror_x(point), mirror_y(point), mirror_x(&mirror_y(point))],
+            Symmetry::Rotational(n) => {
+                let (dx, dy) = (point.x - center.x, point.y - center.y);
+                (0..*n)
+                    .map(|i| {
+                        let (sin, cos) = (i as f64 * std::f64::consts::TAU / *n as f64).sin_cos();
+                        Point { x: center.x + dx * cos - dy * sin, y: center.y + dx * sin + dy * cos }
+                    })
+                    .collect()
+            }
+        };
+
+        let mut unique: Vec<Point> = Vec::with_capacity(images.len());
+        for p in images {
+            if unique.iter().all(|u| (u.x - p.x).abs() > SAME_SITE_EPSILON || (u.y - p.y).abs() > SAME_SITE_EPSILON) {
+                unique.push(p);
+            }
+        }
+
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER: Point = Point { x: 1.0, y: 1.0 };
+
+    #[test]
+    fn mirror_xy_expect_four_copies() {
+        let images = Symmetry::MirrorXY.images(&Point { x: 1.5, y: 1.25 }, &CENTER);
+        let coords: Vec<(f64, f64)> = images.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(vec![(1.5, 1.25), (1.5, 0.75), (0.5, 1.25), (0.5, 0.75)], coords);
+    }
+
+    #[test]
+    fn mirror_on_axis_expect_single_copy() {
+        let images = Symmetry::MirrorX.images(&Point { x: 0.3, y: 1.0 }, &CENTER);
+        assert_eq!(1, images.len());
+    }
+
+    #[test]
+    fn rotational_expect_copies_around_center() {
+        let images = Symmetry::Rotational(4).images(&Point { x: 2.0, y: 1.0 }, &CENTER);
+        assert_eq!(4, images.len());
+        for p in images.iter() {
+            let r = ((p.x - CENTER.x).powi(2) + (p.y - CENTER.y).powi(2)).sqrt();
+            assert!((r - 1.0).abs() < 1e-12);
+        }
+        assert!((images[1].x - 1.0).abs() < 1e-12);
+        assert!((images[1].y - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rotational_at_center_expect_single_copy() {
+        let images = Symmetry::Rotational(6).images(&CENTER, &CENTER);
+        assert_eq!(1, images.len());
+    }
+}
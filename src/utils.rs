@@ -1,5 +1,8 @@
-use bevy::prelude::Color;
-use voronoice::Point;
+use bevy::{
+    prelude::Color,
+    render::{mesh::{Indices, Mesh}, pipeline::PrimitiveTopology},
+};
+use voronoice::{BoundingBox, Point};
 
 #[inline]
 pub fn point_to_f32_vec(p: &Point) -> [f32; 3] {
@@ -14,8 +17,49 @@ pub fn to_f32_vec(p: &Vec<Point>) -> Vec<[f32; 3]> {
         .collect()
 }
 
-pub fn color_to_f32_vec(color: Color) -> [f32; 3] {
-    [color.r(), color.g(), color.b()]
+#[inline]
+pub fn distance(a: &Point, b: &Point) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+/// Corners of the bounding box, counterclockwise from the one with the smallest coordinates.
+pub fn bounding_box_corners(bounding_box: &BoundingBox) -> [Point; 4] {
+    let center = bounding_box.center();
+    let (w, h) = (bounding_box.width() / 2.0, bounding_box.height() / 2.0);
+    [
+        Point { x: center.x - w, y: center.y - h },
+        Point { x: center.x + w, y: center.y - h },
+        Point { x: center.x + w, y: center.y + h },
+        Point { x: center.x - w, y: center.y + h },
+    ]
+}
+
+pub fn color_to_f32_vec(color: Color) -> [f32; 4] {
+    [color.r(), color.g(), color.b(), color.a()]
+}
+
+/// Builds a line strip through `points`, back to the first one if `closed`.
+pub fn build_line_strip(points: &[Point], closed: bool, color: Color) -> Mesh {
+    let positions: Vec<[f32; 3]> = points.iter().map(point_to_f32_vec).collect();
+    let num_of_vertices = positions.len();
+    let mut indices: Vec<u32> = (0..num_of_vertices as u32).collect();
+    if closed && num_of_vertices > 0 {
+        indices.push(0);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; num_of_vertices]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; num_of_vertices]);
+    mesh.set_attribute("Vertex_Color", vec![color_to_f32_vec(color); num_of_vertices]);
+    mesh
+}
+
+/// Sets every vertex of `mesh` to `color`.
+pub fn set_mesh_color(mesh: &mut Mesh, color: Color) {
+    let num_of_vertices = mesh.count_vertices();
+    mesh.set_attribute("Vertex_Color", vec![color_to_f32_vec(color); num_of_vertices]);
 }
 
 /// Returns an iterator that interleaves sequential pairs. The last element is paired with the first.
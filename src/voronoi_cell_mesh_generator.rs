@@ -28,7 +28,7 @@ impl VoronoiCellMeshGenerator<'_> {
         let num_of_vertices = positions.len();
         let normals: Vec<[f32; 3]> = vec![[0.0, 1.0, 0.0]; num_of_vertices];
         let uvs: Vec<[f32; 2]> = vec![[0.0, 0.0]; num_of_vertices];
-        let colors: Vec<[f32; 3]> = (0..num_of_vertices)
+        let colors: Vec<[f32; 4]> = (0..num_of_vertices)
             .map(self.coloring)
             .map(utils::color_to_f32_vec)
             .collect();
@@ -43,6 +43,31 @@ impl VoronoiCellMeshGenerator<'_> {
         mesh
     }
 
+    /// Builds a closed line strip around the cell.
+    pub fn build_outline_mesh(&self) -> Mesh {
+        let vertices: Vec<Point> = self.cell.iter_vertices().cloned().collect();
+        let positions: Vec<[f32; 3]> = utils::to_f32_vec(&vertices);
+
+        let num_of_vertices = positions.len();
+        let normals: Vec<[f32; 3]> = vec![[0.0, 1.0, 0.0]; num_of_vertices];
+        let uvs: Vec<[f32; 2]> = vec![[0.0, 0.0]; num_of_vertices];
+        let colors: Vec<[f32; 4]> = (0..num_of_vertices)
+            .map(self.coloring)
+            .map(utils::color_to_f32_vec)
+            .collect();
+        let indices: Vec<u32> = (0..num_of_vertices as u32)
+            .chain(once(0))
+            .collect();
+
+        let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.set_attribute("Vertex_Color", colors);
+        mesh
+    }
+
     fn build_voronoi_cell_index_buffer(&self, num_of_vertices: u32) -> Vec<u32> {
         (0..num_of_vertices)
             .chain(once(1)) // add first cell vertex (not the 0 which is the site position) to the end so it gets a triangle fanned with the last record
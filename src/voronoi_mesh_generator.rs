@@ -21,7 +21,7 @@ impl VoronoiMeshGenerator<'_> {
         let num_of_vertices = positions.len();
         let normals: Vec<[f32; 3]> = vec![[0.0, 1.0, 0.0]; num_of_vertices];
         let uvs: Vec<[f32; 2]> = vec![[0.0, 0.0]; num_of_vertices];
-        let colors: Vec<[f32; 3]> = (0..num_of_vertices)
+        let colors: Vec<[f32; 4]> = (0..num_of_vertices)
             .map(self.coloring)
             .map(utils::color_to_f32_vec)
             .collect();
@@ -53,7 +53,7 @@ impl VoronoiMeshGenerator<'_> {
         let num_of_vertices = positions.len();
         let normals: Vec<[f32; 3]> = vec![[0.0, 1.0, 0.0]; num_of_vertices];
         let uvs: Vec<[f32; 2]> = vec![[0.0, 0.0]; num_of_vertices];
-        let colors: Vec<[f32; 3]> = (0..num_of_vertices)
+        let colors: Vec<[f32; 4]> = (0..num_of_vertices)
             .map(self.coloring)
             .map(utils::color_to_f32_vec)
             .collect();